@@ -1,18 +1,17 @@
-use solana_client::rpc_response::SlotInfo;
+use futures::StreamExt;
 use solana_client_async::prelude::*;
 
 #[tokio::main]
 async fn main() {
-    let mut client = ClientBuilder::new()
+    let client = ClientBuilder::new()
         .ws_url("wss://api.mainnet-beta.solana.com")
         .build()
         .await
         .unwrap();
 
-    let _subscription_id: u64 = client.slot_subscribe().await.unwrap().await.unwrap(); // Double await because the first await is for `Send` and the second one for `Receive`. It is fine to drop the second one.
+    let mut subscription = client.slot_subscribe().await.unwrap();
 
-    loop {
-        let slot = client.recv::<SlotInfo>().await.unwrap();
-        println!("slot {:?}", slot);
+    while let Some(slot) = subscription.next().await {
+        println!("slot {:?}", slot.unwrap());
     }
 }
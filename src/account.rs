@@ -0,0 +1,187 @@
+use crate::client::Client;
+use crate::errors::{Result as MyResult, SolanaClientError};
+use fehler::{throw, throws};
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::{from_value, Value};
+use solana_account_decoder::parse_account_data::ParsedAccount;
+use solana_account_decoder::parse_address_lookup_table::LookupTableAccountType;
+use solana_account_decoder::parse_bpf_loader::BpfUpgradeableLoaderAccountType;
+use solana_account_decoder::parse_config::ConfigAccountType;
+use solana_account_decoder::parse_nonce::UiNonceState;
+use solana_account_decoder::parse_stake::StakeAccountType;
+use solana_account_decoder::parse_sysvar::SysvarAccountType;
+use solana_account_decoder::parse_token::TokenAccountType;
+use solana_account_decoder::parse_vote::VoteAccountType;
+use solana_account_decoder::{UiAccount, UiAccountData};
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_sdk::account::Account as RawAccount;
+use solana_sdk::clock::Epoch;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// An account with its data decoded from the RPC encoding.
+#[derive(Debug, PartialEq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: AccountData,
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub rent_epoch: Epoch,
+}
+
+#[derive(Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum AccountData {
+    /// The bytes of the Binary, Base58, Base64 and Base64+Zstd encodings.
+    Bytes(Vec<u8>),
+    /// The `jsonParsed` encoding.
+    Parsed(ParsedAccountData),
+}
+
+/// The `jsonParsed` data of the programs known to `solana-account-decoder`.
+#[derive(Debug, PartialEq)]
+pub enum ParsedAccountData {
+    /// SPL Token and Token-2022 accounts, mints and multisigs.
+    Token(TokenAccountType),
+    Stake(StakeAccountType),
+    Vote(VoteAccountType),
+    Nonce(UiNonceState),
+    Sysvar(SysvarAccountType),
+    Config(ConfigAccountType),
+    BpfUpgradeableLoader(BpfUpgradeableLoaderAccountType),
+    AddressLookupTable(LookupTableAccountType),
+    /// A program this crate does not know, left as JSON.
+    Other(ParsedAccount),
+}
+
+/// An account of a program subscription, with its address.
+#[derive(Debug, PartialEq)]
+pub struct KeyedAccount {
+    pub pubkey: Pubkey,
+    pub account: Account,
+}
+
+impl Account {
+    #[throws(SolanaClientError)]
+    pub fn decode(account: &UiAccount) -> Self {
+        let owner = Pubkey::from_str(&account.owner)
+            .map_err(|e| SolanaClientError::Decode(format!("Invalid owner: {}", e)))?;
+        let data = match &account.data {
+            UiAccountData::Json(parsed) => AccountData::Parsed(ParsedAccountData::decode(parsed)?),
+            data => match account.decode::<RawAccount>() {
+                Some(raw) => AccountData::Bytes(raw.data),
+                None => throw!(SolanaClientError::Decode(format!(
+                    "Invalid data {:?}",
+                    data
+                ))),
+            },
+        };
+
+        Self {
+            lamports: account.lamports,
+            data,
+            owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        }
+    }
+
+    /// The raw bytes, `None` for the `jsonParsed` encoding.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.data {
+            AccountData::Bytes(bytes) => Some(bytes),
+            AccountData::Parsed(_) => None,
+        }
+    }
+
+    pub fn parsed(&self) -> Option<&ParsedAccountData> {
+        match &self.data {
+            AccountData::Bytes(_) => None,
+            AccountData::Parsed(parsed) => Some(parsed),
+        }
+    }
+}
+
+impl KeyedAccount {
+    #[throws(SolanaClientError)]
+    pub fn decode(keyed: &RpcKeyedAccount) -> Self {
+        Self {
+            pubkey: Pubkey::from_str(&keyed.pubkey)
+                .map_err(|e| SolanaClientError::Decode(format!("Invalid pubkey: {}", e)))?,
+            account: Account::decode(&keyed.account)?,
+        }
+    }
+}
+
+impl ParsedAccountData {
+    /// Dispatch on the program name given by the RPC.
+    #[throws(SolanaClientError)]
+    pub fn decode(parsed: &ParsedAccount) -> Self {
+        use ParsedAccountData::*;
+
+        match parsed.program.as_str() {
+            "spl-token" | "spl-token-2022" => Token(typed(parsed)?),
+            "stake" => Stake(typed(parsed)?),
+            "vote" => Vote(typed(parsed)?),
+            "nonce" => Nonce(typed(parsed)?),
+            "sysvar" => Sysvar(typed(parsed)?),
+            "config" => Config(typed(parsed)?),
+            "bpf-upgradeable-loader" => BpfUpgradeableLoader(typed(parsed)?),
+            "address-lookup-table" => AddressLookupTable(typed(parsed)?),
+            _ => Other(parsed.clone()),
+        }
+    }
+}
+
+#[throws(SolanaClientError)]
+fn typed<T: DeserializeOwned>(parsed: &ParsedAccount) -> T {
+    from_value(Value::clone(&parsed.parsed)).map_err(|e| {
+        SolanaClientError::Decode(format!("Invalid {} account: {}", parsed.program, e))
+    })?
+}
+
+impl Client {
+    /// Like `account_subscribe`, with the account data decoded.
+    #[throws(SolanaClientError)]
+    pub async fn account_subscribe_decoded(
+        &self,
+        pubkey: &Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> impl Stream<Item = MyResult<Response<Account>>> {
+        self.account_subscribe(pubkey, config)
+            .await?
+            .map(decode_account)
+    }
+
+    /// Like `program_subscribe`, with the account data decoded.
+    #[throws(SolanaClientError)]
+    pub async fn program_subscribe_decoded(
+        &self,
+        program_id: &Pubkey,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> impl Stream<Item = MyResult<Response<KeyedAccount>>> {
+        self.program_subscribe(program_id, config)
+            .await?
+            .map(decode_keyed)
+    }
+}
+
+#[throws(SolanaClientError)]
+fn decode_account(resp: MyResult<Response<UiAccount>>) -> Response<Account> {
+    let resp = resp?;
+    Response {
+        value: Account::decode(&resp.value)?,
+        context: resp.context,
+    }
+}
+
+#[throws(SolanaClientError)]
+fn decode_keyed(resp: MyResult<Response<RpcKeyedAccount>>) -> Response<KeyedAccount> {
+    let resp = resp?;
+    Response {
+        value: KeyedAccount::decode(&resp.value)?,
+        context: resp.context,
+    }
+}
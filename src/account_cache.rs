@@ -0,0 +1,250 @@
+use crate::backpressure::Backpressure;
+use crate::client::Client;
+use crate::errors::{Result as MyResult, SolanaClientError};
+use crate::http_client::HttpClient;
+use crate::subscription::Subscription;
+use fehler::throws;
+use futures::{future::try_join_all, stream, Stream, StreamExt};
+use log::{debug, warn};
+use serde_json::json;
+use solana_account_decoder::UiAccount;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_response::{OptionalContext, Response};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tokio::spawn;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+// Maximum number of accounts per getMultipleAccounts call, and per subscribe batch.
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+type Updates =
+    Pin<Box<dyn Stream<Item = (Option<Pubkey>, MyResult<(Slot, Pubkey, UiAccount)>)> + Send>>;
+
+/// The state of an account as of a slot, `None` if the account does not exist,
+/// including once closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedAccount {
+    pub pubkey: Pubkey,
+    pub slot: Slot,
+    pub account: Option<UiAccount>,
+}
+
+struct Shared {
+    accounts: RwLock<HashMap<Pubkey, CachedAccount>>,
+    changes: broadcast::Sender<CachedAccount>,
+}
+
+/// Aborts the update task once every handle is dropped, which unsubscribes.
+struct Guard(JoinHandle<()>);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A local mirror of accounts, made of a snapshot fetched over HTTP kept up to date
+/// by the websocket notifications. Updates older than the cached state are discarded.
+/// Cloning it is cheap.
+#[derive(Clone)]
+pub struct AccountCache {
+    shared: Arc<Shared>,
+    _guard: Arc<Guard>,
+}
+
+impl AccountCache {
+    /// Mirror the given accounts.
+    #[throws(SolanaClientError)]
+    pub async fn accounts(
+        client: &Client,
+        http: &HttpClient,
+        pubkeys: &[Pubkey],
+        config: RpcAccountInfoConfig,
+    ) -> Self {
+        // Subscribe before the snapshot so no update falls in between
+        let client = client.with_backpressure(Backpressure::Unbounded);
+        // One batch per chunk instead of a round trip per account
+        let mut subscriptions = vec![];
+        for pubkeys in pubkeys.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+            let mut batch = client.batch();
+            for pubkey in pubkeys {
+                let params = json! {[pubkey.to_string(), config]};
+                subscriptions.push(batch.subscribe("accountSubscribe", &params)?);
+            }
+            batch.send().await?;
+        }
+        let subscriptions: Vec<Subscription<Response<UiAccount>>> =
+            try_join_all(subscriptions).await?;
+        let updates = pubkeys
+            .iter()
+            .zip(subscriptions)
+            .map(|(&pubkey, subscription)| {
+                subscription.map(move |resp| {
+                    (
+                        Some(pubkey),
+                        resp.map(|resp| (resp.context.slot, pubkey, resp.value)),
+                    )
+                })
+            });
+
+        let mut snapshot = vec![];
+        let mut snapshot_slot = Slot::MAX;
+        for pubkeys in pubkeys.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+            let resp = http
+                .get_multiple_accounts(pubkeys, Some(config.clone()))
+                .await?;
+            snapshot_slot = snapshot_slot.min(resp.context.slot);
+            for (&pubkey, account) in pubkeys.iter().zip(resp.value) {
+                snapshot.push(CachedAccount {
+                    pubkey,
+                    slot: resp.context.slot,
+                    account: account.and_then(existing),
+                });
+            }
+        }
+
+        Self::start(
+            snapshot,
+            snapshot_slot,
+            Box::pin(stream::select_all(updates)),
+        )
+    }
+
+    /// Mirror the accounts owned by the program, matching the filters of the config.
+    #[throws(SolanaClientError)]
+    pub async fn program(
+        client: &Client,
+        http: &HttpClient,
+        program_id: &Pubkey,
+        mut config: RpcProgramAccountsConfig,
+    ) -> Self {
+        let client = client.with_backpressure(Backpressure::Unbounded);
+        let subscription = client
+            .program_subscribe(program_id, Some(config.clone()))
+            .await?;
+        let updates = subscription.map(|resp| {
+            let update = match resp {
+                Ok(resp) => parse_pubkey(&resp.value.pubkey)
+                    .map(|pubkey| (resp.context.slot, pubkey, resp.value.account)),
+                Err(e) => Err(e),
+            };
+            (None, update)
+        });
+
+        config.with_context = Some(true);
+        let (slot, accounts) = match http.get_program_accounts(program_id, Some(config)).await? {
+            OptionalContext::Context(resp) => (resp.context.slot, resp.value),
+            OptionalContext::NoContext(accounts) => (0, accounts),
+        };
+
+        let mut snapshot = vec![];
+        for keyed in accounts {
+            snapshot.push(CachedAccount {
+                pubkey: parse_pubkey(&keyed.pubkey)?,
+                slot,
+                account: existing(keyed.account),
+            });
+        }
+
+        Self::start(snapshot, slot, Box::pin(updates))
+    }
+
+    fn start(snapshot: Vec<CachedAccount>, snapshot_slot: Slot, updates: Updates) -> Self {
+        let accounts = snapshot
+            .into_iter()
+            .map(|cached| (cached.pubkey, cached))
+            .collect();
+        let (changes, _) = broadcast::channel(1024);
+        let shared = Arc::new(Shared {
+            accounts: RwLock::new(accounts),
+            changes,
+        });
+
+        let task = spawn(apply(shared.clone(), snapshot_slot, updates));
+        Self {
+            shared,
+            _guard: Arc::new(Guard(task)),
+        }
+    }
+
+    /// The latest state of the account, `None` if it is not mirrored.
+    pub fn get(&self, pubkey: &Pubkey) -> Option<CachedAccount> {
+        self.shared.accounts.read().unwrap().get(pubkey).cloned()
+    }
+
+    /// The latest state of every mirrored account.
+    pub fn all(&self) -> Vec<CachedAccount> {
+        self.shared
+            .accounts
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// A receiver of the updates applied from now on.
+    pub fn changes(&self) -> broadcast::Receiver<CachedAccount> {
+        self.shared.changes.subscribe()
+    }
+}
+
+async fn apply(shared: Arc<Shared>, snapshot_slot: Slot, mut updates: Updates) {
+    while let Some((pubkey, update)) = updates.next().await {
+        let (slot, pubkey, account) = match update {
+            Ok(update) => update,
+            Err(e) => {
+                warn!("[AccountCache] Update for {:?} failed: {}", pubkey, e);
+                continue;
+            }
+        };
+
+        if slot < snapshot_slot {
+            debug!(
+                "[AccountCache] Discarding {} at slot {}, older than the snapshot",
+                pubkey, slot
+            );
+            continue;
+        }
+
+        let cached = CachedAccount {
+            pubkey,
+            slot,
+            account: existing(account),
+        };
+        {
+            let mut accounts = shared.accounts.write().unwrap();
+            match accounts.get(&pubkey) {
+                Some(current) if current.slot > slot => {
+                    debug!(
+                        "[AccountCache] Discarding {} at slot {}, cached at {}",
+                        pubkey, slot, current.slot
+                    );
+                    continue;
+                }
+                _ => {
+                    accounts.insert(pubkey, cached.clone());
+                }
+            }
+        }
+        let _ = shared.changes.send(cached);
+    }
+    debug!("[AccountCache] Updates ended");
+}
+
+/// Closed accounts are notified with 0 lamports, the snapshot has them as missing.
+fn existing(account: UiAccount) -> Option<UiAccount> {
+    Some(account).filter(|account| account.lamports > 0)
+}
+
+#[throws(SolanaClientError)]
+fn parse_pubkey(pubkey: &str) -> Pubkey {
+    Pubkey::from_str(pubkey)
+        .map_err(|e| SolanaClientError::Upstream(format!("Invalid pubkey {}: {}", pubkey, e)))?
+}
@@ -0,0 +1,226 @@
+use crate::client::Client;
+use crate::errors::{Result as MyResult, SolanaClientError};
+use crate::subscription::Subscription;
+use fehler::throws;
+use futures::{
+    task::{Context, Poll},
+    Stream,
+};
+use log::debug;
+use solana_account_decoder::UiAccount;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_client::rpc_response::{Response, SlotUpdate};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// The state of an account at both commitments, yielded on every update of either.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AccountView {
+    /// The latest processed value, or the settled one if it is newer.
+    pub optimistic: Option<Response<UiAccount>>,
+    /// The latest value at the settled commitment.
+    pub settled: Option<Response<UiAccount>>,
+    /// Processed updates this update found rolled back: the settled commitment
+    /// reached their slot with a different value than the last of them.
+    /// A settled notification may cover several slots, so they are judged together.
+    /// Without a settled notification, they are judged once the settled commitment
+    /// moved past them, against the settled value they failed to change.
+    pub rolled_back: Vec<Response<UiAccount>>,
+}
+
+impl Client {
+    /// Subscribe to the account at `processed` and at the `settled` commitment,
+    /// usually `confirmed` or `finalized`, merging both into a single view.
+    /// Also follows the slots reaching the settled commitment, to find rollbacks
+    /// the account notifications alone don't tell. The commitment of the config is ignored.
+    #[throws(SolanaClientError)]
+    pub async fn account_subscribe_dual(
+        &self,
+        pubkey: &Pubkey,
+        settled: CommitmentConfig,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> DualAccountSubscription {
+        let config = config.unwrap_or_default();
+        let processed_sub = self
+            .account_subscribe(
+                pubkey,
+                Some(RpcAccountInfoConfig {
+                    commitment: Some(CommitmentConfig::processed()),
+                    ..config.clone()
+                }),
+            )
+            .await?;
+        let settled_sub = self
+            .account_subscribe(
+                pubkey,
+                Some(RpcAccountInfoConfig {
+                    commitment: Some(settled),
+                    ..config
+                }),
+            )
+            .await?;
+        let slots_sub = self.slots_updates_subscribe().await?;
+
+        DualAccountSubscription {
+            processed_sub,
+            settled_sub,
+            slots_sub,
+            finalized: settled.is_finalized(),
+            settled_slot: None,
+            pending: VecDeque::new(),
+            view: AccountView::default(),
+        }
+    }
+}
+
+/// A stream of the merged view of an account at two commitments,
+/// ending when any of its subscriptions ends.
+pub struct DualAccountSubscription {
+    processed_sub: Subscription<Response<UiAccount>>,
+    settled_sub: Subscription<Response<UiAccount>>,
+    slots_sub: Subscription<SlotUpdate>,
+    // Follow the rooted slots rather than the optimistically confirmed ones.
+    finalized: bool,
+    // The last slot the settled commitment reached.
+    settled_slot: Option<Slot>,
+    // Processed updates not settled yet, oldest first.
+    pending: VecDeque<Response<UiAccount>>,
+    view: AccountView,
+}
+
+impl DualAccountSubscription {
+    /// The view as of the last update.
+    pub fn view(&self) -> &AccountView {
+        &self.view
+    }
+
+    /// Returns false if the update is already settled, leaving the view as is.
+    fn on_processed(&mut self, resp: Response<UiAccount>) -> bool {
+        let settled_slot = self.view.settled.as_ref().map(|s| s.context.slot);
+        if matches!(settled_slot, Some(slot) if resp.context.slot <= slot) {
+            // The settled stream was faster
+            return false;
+        }
+        self.pending.push_back(resp.clone());
+        self.view.optimistic = Some(resp);
+        true
+    }
+
+    fn on_settled(&mut self, resp: Response<UiAccount>) {
+        let slot = resp.context.slot;
+        let reached = self
+            .pending
+            .iter()
+            .take_while(|pending| pending.context.slot <= slot)
+            .count();
+        let reached: Vec<_> = self.pending.drain(..reached).collect();
+        if matches!(reached.last(), Some(last) if last.value != resp.value) {
+            debug!(
+                "[AccountView] {} processed updates rolled back at slot {}",
+                reached.len(),
+                slot
+            );
+            self.view.rolled_back = reached;
+        }
+
+        let optimistic_slot = self.view.optimistic.as_ref().map(|o| o.context.slot);
+        if !matches!(optimistic_slot, Some(optimistic) if optimistic > slot) {
+            self.view.optimistic = Some(resp.clone());
+        }
+        self.view.settled = Some(resp);
+    }
+
+    /// Returns true if processed updates were found rolled back.
+    fn on_slot(&mut self, update: SlotUpdate) -> bool {
+        let slot = match update {
+            SlotUpdate::OptimisticConfirmation { slot, .. } if !self.finalized => slot,
+            SlotUpdate::Root { slot, .. } if self.finalized => slot,
+            _ => return false,
+        };
+        // Judge up to the slot reached before, the settled notification of the account
+        // at the slot just reached may still be on its way
+        let previous = match self.settled_slot {
+            Some(previous) if previous >= slot => return false,
+            previous => previous,
+        };
+        self.settled_slot = Some(slot);
+        let judged = match previous {
+            Some(judged) => judged,
+            None => return false,
+        };
+        let reached = self
+            .pending
+            .iter()
+            .take_while(|pending| pending.context.slot <= judged)
+            .count();
+        let settled = self.view.settled.as_ref().map(|settled| &settled.value);
+        let rolled_back: Vec<_> = self
+            .pending
+            .drain(..reached)
+            .filter(|pending| Some(&pending.value) != settled)
+            .collect();
+        if rolled_back.is_empty() {
+            return false;
+        }
+
+        debug!(
+            "[AccountView] {} processed updates rolled back by slot {}",
+            rolled_back.len(),
+            judged
+        );
+        self.view.rolled_back = rolled_back;
+        if self.pending.is_empty() {
+            self.view.optimistic = self.view.settled.clone();
+        }
+        true
+    }
+}
+
+impl Stream for DualAccountSubscription {
+    type Item = MyResult<AccountView>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.view.rolled_back.clear();
+
+        // Settled first, so that a rollback is reported as early as possible
+        match Pin::new(&mut this.settled_sub).poll_next(cx) {
+            Poll::Ready(Some(Ok(resp))) => {
+                this.on_settled(resp);
+                return Poll::Ready(Some(Ok(this.view.clone())));
+            }
+            Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        loop {
+            match Pin::new(&mut this.slots_sub).poll_next(cx) {
+                Poll::Ready(Some(Ok(update))) => {
+                    if this.on_slot(update) {
+                        return Poll::Ready(Some(Ok(this.view.clone())));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => break,
+            }
+        }
+
+        loop {
+            match Pin::new(&mut this.processed_sub).poll_next(cx) {
+                Poll::Ready(Some(Ok(resp))) => {
+                    if this.on_processed(resp) {
+                        return Poll::Ready(Some(Ok(this.view.clone())));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -1,118 +1,497 @@
+use crate::backpressure::Notifier;
+use crate::client::ClientBuilder;
 use crate::errors::{Result as MyResult, SolanaClientError};
 use crate::rpc_message::{RpcError, RpcNotification, RpcRequest, RpcResponse};
+use crate::watchdog::{OnStall, Watchdog};
 use crate::{Responder, WsStream};
 use fehler::{throw, throws};
-use futures::{SinkExt, StreamExt};
-use log::{debug, error, trace, warn};
+use futures::{pin_mut, Future, SinkExt, StreamExt};
+use log::{debug, error, info, trace, warn};
 use serde::Deserialize;
 use serde_json::value::RawValue;
 use serde_json::{from_str, to_string};
-use std::{collections::HashMap, time::Duration};
+use solana_client::rpc_response::{Response, RpcSignatureResult};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
 use tokio::{
     select, spawn,
-    sync::{broadcast, mpsc, oneshot},
-    time::{interval, Interval},
+    sync::{broadcast, mpsc, oneshot, watch},
+    task::JoinHandle,
+    time::{self, interval, sleep, sleep_until, Instant, Interval},
 };
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
 use tungstenite::Message;
 
+// Maximum number of notifications held for a subscription whose id is not confirmed yet.
+const EARLY_NOTIFICATION_LIMIT: usize = 1024;
+// How long to wait for the server to acknowledge the close when all handles are dropped
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A command sent from the client handles to the background process.
+#[derive(Debug)]
+pub enum Control {
+    /// The subscription handle is dropped.
+    Unsubscribe(u64),
+    /// Unsubscribe everything and close the websocket.
+    Shutdown {
+        frame: CloseFrame<'static>,
+        timeout: Duration,
+        done: oneshot::Sender<()>,
+    },
+}
+
+/// A request sent from the client to the background process.
+#[derive(Debug)]
+pub struct Request {
+    pub(crate) method: String,
+    pub(crate) params: Box<RawValue>,
+    pub(crate) responder: Responder,
+    // Where the notifications go if this is a subscription, otherwise
+    // they are broadcast to `Client::recv`.
+    pub(crate) notifier: Option<Notifier>,
+    pub(crate) watchdog: Option<Watchdog>,
+}
+
+enum Pending {
+    /// A request issued by the client, kept around so it can be resent after a reconnect.
+    Request(Request),
+    /// A subscription replayed by the background process after a reconnect.
+    Resubscribe { subid: u64, method: String },
+    /// An unsubscribe issued by the background process itself, the response is ignored.
+    Cleanup,
+}
+
+struct LiveSubscription {
+    method: String,
+    params: Box<RawValue>,
+    server_id: Option<u64>,
+    notifier: Option<Notifier>,
+    watchdog: Option<Watchdog>,
+    // When the last notification arrived, or when the subscription got confirmed.
+    last_notif: Instant,
+}
+
 pub struct BackgroundProcess {
-    pendings: HashMap<u64, oneshot::Sender<Result<RpcResponse, RpcError>>>,
+    builder: ClientBuilder,
+    pendings: HashMap<u64, Pending>,
+    // Subscription ids handed out to the client, which stay stable across reconnects.
+    subscriptions: HashMap<u64, LiveSubscription>,
+    // Subscription ids of the current connection to the ones handed out to the client.
+    server_ids: HashMap<u64, u64>,
+    // Notifications that arrive before the response carrying their subscription id.
+    early_notifs: HashMap<u64, VecDeque<RpcNotification>>,
     ws: WsStream,
+    // Index of the connected endpoint in `ClientBuilder::endpoints`.
+    endpoint: usize,
+    // When the last message arrived, to detect a stale connection.
+    last_seen: Instant,
+    // Sequence number and send time of the last ping, to measure the latency.
+    ping_seq: u64,
+    ping_sent: Option<Instant>,
+    latency_tx: watch::Sender<Option<Duration>>,
+    // No watchdog expires before this, checked again when reached.
+    watchdog_deadline: Option<Instant>,
     sub_tx: broadcast::Sender<MyResult<RpcNotification>>,
-    request_rx: mpsc::Receiver<(String, Box<RawValue>, Responder)>,
+    request_rx: mpsc::Receiver<Vec<Request>>,
+    requests_closed: bool,
+    closed_tx: watch::Sender<Option<SolanaClientError>>,
+    control_rx: mpsc::UnboundedReceiver<Control>,
+    control_closed: bool,
+    shutdown_done: Option<oneshot::Sender<()>>,
     ping_timer: Interval,
     reqid: u64,
+    subid: u64,
 }
 
 impl BackgroundProcess {
     #[allow(clippy::type_complexity)]
     pub fn new(
         stream: WsStream,
-        ping_every: u64,
+        endpoint: usize,
+        builder: ClientBuilder,
     ) -> (
         Self,
         broadcast::Receiver<MyResult<RpcNotification>>,
-        mpsc::Sender<(String, Box<RawValue>, Responder)>,
+        mpsc::Sender<Vec<Request>>,
+        mpsc::UnboundedSender<Control>,
+        watch::Receiver<Option<SolanaClientError>>,
+        watch::Receiver<Option<Duration>>,
     ) {
         let (request_tx, request_rx) = mpsc::channel(1024);
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let (closed_tx, closed_rx) = watch::channel(None);
+        let (latency_tx, latency_rx) = watch::channel(None);
         let (sub_tx, sub_rx) = broadcast::channel(1024);
-        let ping_timer = interval(Duration::from_secs(ping_every));
+        let ping_timer = interval(builder.ping_interval());
 
         (
             Self {
+                builder,
                 pendings: HashMap::new(),
+                subscriptions: HashMap::new(),
+                server_ids: HashMap::new(),
+                early_notifs: HashMap::new(),
                 ws: stream,
+                endpoint,
+                last_seen: Instant::now(),
+                ping_seq: 0,
+                ping_sent: None,
+                latency_tx,
+                watchdog_deadline: None,
                 sub_tx,
                 request_rx,
+                requests_closed: false,
+                closed_tx,
+                control_rx,
+                control_closed: false,
+                shutdown_done: None,
                 ping_timer,
                 reqid: 0,
+                subid: 0,
             },
             sub_rx,
             request_tx,
+            control_tx,
+            closed_rx,
+            latency_rx,
         )
     }
 
-    pub fn start(self) {
+    pub fn start(self) -> JoinHandle<()> {
         spawn(async {
             match self.start_impl().await {
                 Ok(_) => {
-                    unreachable!()
+                    info!("[Background] Shut down")
                 }
                 Err(e) => {
                     error!("[Background] Exited due to error: {}", e)
                 }
             }
-        });
+        })
     }
 
+    /// Run the background process. When it fails, the error is handed to every
+    /// pending request, live subscription and `Client::closed`.
     pub async fn start_impl(mut self) -> Result<(), SolanaClientError> {
+        let result = self.run().await;
+        match &result {
+            Ok(()) => self.fail_all(&SolanaClientError::Shutdown).await,
+            Err(e) => self.fail_all(e).await,
+        }
+        if let Some(done) = self.shutdown_done.take() {
+            let _ = done.send(());
+        }
+        result
+    }
+
+    async fn run(&mut self) -> MyResult<()> {
+        loop {
+            match self.serve().await {
+                Err(e) if e.is_disconnect() => {
+                    warn!("[Background] Connection lost: {}, reconnecting", e);
+                    if !self.reconnect(e).await? {
+                        return Ok(());
+                    }
+                }
+                Err(e) => throw!(e),
+                Ok(()) => return Ok(()),
+            }
+        }
+    }
+
+    async fn serve(&mut self) -> MyResult<()> {
+        self.replay().await?;
+
         loop {
             select! {
-                _ = self.ping_timer.tick() => { self.ping().await? }
-                msg = self.ws.next() => {
-                    if msg.is_none() {
-                        let _ = self.sub_tx.send(Err(SolanaClientError::WsClosed(None)));
-                        throw!(SolanaClientError::WsClosed(None));
+                _ = self.ping_timer.tick() => {
+                    let stale_timeout = self.builder.stale_after();
+                    if self.last_seen.elapsed() > stale_timeout {
+                        throw!(SolanaClientError::Stale(stale_timeout));
                     }
-                    if let Err(e) = self.process_ws(msg.unwrap()?).await {
-                        let _ = self.sub_tx.send(Err(e.clone()));
-                        throw!(e);
+                    self.purge_pendings();
+                    self.ping().await?
+                }
+                msg = self.ws.next() => {
+                    match msg {
+                        Some(msg) => self.process_ws(msg?).await?,
+                        None => throw!(SolanaClientError::WsClosed(None)),
                     }
                 }
-                req = self.request_rx.recv() => {
-                    if req.is_none() {
-                        warn!("Request rx exited");
-                        break;
+                req = self.request_rx.recv(), if !self.requests_closed => {
+                    match req {
+                        Some(req) => self.process_req(req).await?,
+                        None => {
+                            warn!("Request rx exited");
+                            self.requests_closed = true;
+                        }
                     }
-                    if let Err(e) = self.process_req(req.unwrap()).await {
-                        let _ = self.sub_tx.send(Err(e.clone()));
-                        throw!(e);
+                }
+                _ = sleep_until(self.watchdog_deadline.unwrap_or_else(Instant::now)), if self.watchdog_deadline.is_some() => {
+                    self.check_watchdogs().await?
+                }
+                ctrl = self.control_rx.recv(), if !self.control_closed => {
+                    match ctrl {
+                        Some(Control::Unsubscribe(subid)) => self.unsubscribe(subid).await?,
+                        Some(Control::Shutdown { frame, timeout, done }) => {
+                            self.close(frame, timeout).await;
+                            self.shutdown_done = Some(done);
+                            return Ok(());
+                        }
+                        None => self.control_closed = true,
                     }
                 }
             }
+
+            // Every client handle and subscription is gone, nobody can use the connection anymore
+            if self.requests_closed && self.control_closed {
+                let frame = CloseFrame {
+                    code: CloseCode::Normal,
+                    reason: "".into(),
+                };
+                self.close(frame, CLOSE_TIMEOUT).await;
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reconnect following the reconnect policy, gives up with the last error. Returns
+    /// false if shut down meanwhile, by `Client::shutdown` or all the handles being dropped.
+    async fn reconnect(&mut self, mut error: SolanaClientError) -> MyResult<bool> {
+        let builder = self.builder.clone();
+        let policy = &builder.reconnect_policy;
+        let since = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            let delay = match policy.next_delay(attempt, since.elapsed()) {
+                Some(delay) => delay,
+                None => {
+                    error!(
+                        "[Background] Giving up reconnecting after {} attempts",
+                        attempt
+                    );
+                    policy.give_up(&error);
+                    throw!(error);
+                }
+            };
+            attempt += 1;
+
+            // Start from the next endpoint, which is the same one if there are no failovers
+            let start = self.endpoint + 1;
+            let connect = async {
+                sleep(delay).await;
+                builder.connect(start).await
+            };
+            let connected = match self.disconnected(connect).await {
+                Some(connected) => connected,
+                None => return Ok(false),
+            };
+
+            match connected {
+                Ok((endpoint, ws)) => {
+                    info!(
+                        "[Background] Reconnected to endpoint {} after {} attempts",
+                        endpoint, attempt
+                    );
+                    self.endpoint = endpoint;
+                    self.ws = ws;
+                    self.last_seen = Instant::now();
+                    self.ping_sent = None;
+                    self.latency_tx.send_replace(None);
+                    self.ping_timer.reset();
+                    return Ok(true);
+                }
+                Err(e) => {
+                    warn!("[Background] Reconnect attempt {} failed: {}", attempt, e);
+                    error = e;
+                }
+            }
         }
+    }
+
+    /// Drive `connect` while keeping up with the handles: requests are queued for the
+    /// replay and dropped subscriptions forgotten. `None` if shut down meanwhile.
+    async fn disconnected<T>(&mut self, connect: impl Future<Output = T>) -> Option<T> {
+        pin_mut!(connect);
 
         loop {
+            if self.requests_closed && self.control_closed {
+                info!("[Background] Every handle is dropped, stop reconnecting");
+                return None;
+            }
+
             select! {
-                _ = self.ping_timer.tick() => { self.ping().await? }
-                msg = self.ws.next() => {
-                    if msg.is_none() {
-                        let _ = self.sub_tx.send(Err(SolanaClientError::WsClosed(None)));
-                        throw!(SolanaClientError::WsClosed(None));
+                connected = &mut connect => return Some(connected),
+                req = self.request_rx.recv(), if !self.requests_closed => {
+                    match req {
+                        Some(reqs) => {
+                            for req in reqs {
+                                if let Err(e) = self.prepare_req(req) {
+                                    warn!("[Background] Cannot queue request: {}", e);
+                                }
+                            }
+                        }
+                        None => self.requests_closed = true,
                     }
-                    if let Err(e) = self.process_ws(msg.unwrap()?).await {
-                        let _ = self.sub_tx.send(Err(e.clone()));
-                        throw!(e);
+                }
+                ctrl = self.control_rx.recv(), if !self.control_closed => {
+                    match ctrl {
+                        // The server side subscription died with the connection
+                        Some(Control::Unsubscribe(subid)) => {
+                            self.subscriptions.remove(&subid);
+                        }
+                        Some(Control::Shutdown { done, .. }) => {
+                            info!("[Background] Shut down while reconnecting");
+                            self.shutdown_done = Some(done);
+                            return None;
+                        }
+                        None => self.control_closed = true,
                     }
                 }
             }
         }
     }
 
+    async fn fail_all(&mut self, error: &SolanaClientError) {
+        // Also fail the requests queued but not sent yet
+        self.request_rx.close();
+        while let Ok(reqs) = self.request_rx.try_recv() {
+            for req in reqs {
+                let _ = req.responder.send(Err(error.clone()));
+            }
+        }
+
+        for (_, pending) in self.pendings.drain() {
+            if let Pending::Request(req) = pending {
+                let _ = req.responder.send(Err(error.clone()));
+            }
+        }
+
+        // Subscriptions just end on shutdown
+        for (_, sub) in self.subscriptions.drain() {
+            match sub.notifier {
+                Some(notifier) if !matches!(error, SolanaClientError::Shutdown) => {
+                    notifier.send(Err(error.clone())).await;
+                }
+                _ => {}
+            }
+        }
+
+        let _ = self.sub_tx.send(Err(error.clone()));
+        self.closed_tx.send_replace(Some(error.clone()));
+    }
+
+    /// Unsubscribe the live subscriptions, then close the websocket and wait
+    /// for the server to acknowledge. Failures are only logged since the
+    /// connection is going away anyway.
+    async fn close(&mut self, frame: CloseFrame<'static>, timeout: Duration) {
+        info!("[Background] Closing the websocket: {}", frame);
+
+        let mut subids: Vec<_> = self.subscriptions.keys().copied().collect();
+        subids.sort_unstable();
+        for subid in subids {
+            if let Err(e) = self.unsubscribe(subid).await {
+                warn!("[Background] Cannot unsubscribe {}: {}", subid, e);
+                return;
+            }
+        }
+
+        if let Err(e) = self.ws.close(Some(frame)).await {
+            warn!("[Background] Cannot send the close frame: {}", e);
+            return;
+        }
+
+        let ws = &mut self.ws;
+        let acknowledged = async {
+            while let Some(msg) = ws.next().await {
+                if matches!(msg, Ok(Message::Close(_)) | Err(_)) {
+                    break;
+                }
+            }
+        };
+        if time::timeout(timeout, acknowledged).await.is_err() {
+            warn!("[Background] Server did not close the websocket in time");
+        }
+    }
+
+    /// Drop the pending requests whose awaiter timed out or is dropped. Subscribe requests
+    /// are kept to unsubscribe them if the response arrives.
+    fn purge_pendings(&mut self) {
+        self.pendings.retain(|id, pending| match pending {
+            Pending::Request(req)
+                if req.responder.is_closed() && !req.method.ends_with("Subscribe") =>
+            {
+                debug!("[Background] Dropping abandoned request {}", id);
+                false
+            }
+            _ => true,
+        });
+    }
+
+    /// Resend the in-flight requests and re-issue the live subscriptions on a fresh connection.
+    #[throws(SolanaClientError)]
+    async fn replay(&mut self) {
+        self.server_ids.clear();
+        self.early_notifs.clear();
+        self.purge_pendings();
+        self.pendings
+            .retain(|_, pending| matches!(pending, Pending::Request(_)));
+
+        // The server ids of in-flight unsubscribes belong to the old connection, where
+        // the subscriptions died. Resent, they could hit another subscription.
+        let unsubscribes: Vec<_> = self
+            .pendings
+            .iter()
+            .filter(|(_, pending)| {
+                matches!(pending, Pending::Request(req) if req.method.ends_with("Unsubscribe"))
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        for id in unsubscribes {
+            if let Some(Pending::Request(req)) = self.pendings.remove(&id) {
+                answer(req.responder, id, "true");
+            }
+        }
+
+        let mut reqs = vec![];
+        for (&id, pending) in &self.pendings {
+            if let Pending::Request(req) = pending {
+                reqs.push(RpcRequest::new(id, &req.method, req.params.clone()));
+            }
+        }
+
+        let mut subids: Vec<_> = self.subscriptions.keys().copied().collect();
+        subids.sort_unstable();
+        for subid in subids {
+            let id = self.id();
+            let sub = self.subscriptions.get_mut(&subid).unwrap();
+            sub.server_id = None;
+            reqs.push(RpcRequest::new(id, &sub.method, sub.params.clone()));
+            self.pendings.insert(
+                id,
+                Pending::Resubscribe {
+                    subid,
+                    method: sub.method.clone(),
+                },
+            );
+        }
+
+        for req in reqs {
+            debug!("[Background] Replaying {} with id {}", req.method, req.id);
+            self.ws.send(Message::Text(to_string(&req)?)).await?;
+        }
+    }
+
     #[throws(SolanaClientError)]
     pub async fn ping(&mut self) {
         debug!("[Background] Ping");
-        self.ws.send(Message::Ping(vec![])).await?
+        self.ping_seq += 1;
+        let payload = self.ping_seq.to_be_bytes().to_vec();
+        self.ws.send(Message::Ping(payload)).await?;
+        self.ping_sent = Some(Instant::now());
     }
 
     #[throws(SolanaClientError)]
@@ -124,6 +503,7 @@ impl BackgroundProcess {
     #[throws(SolanaClientError)]
     pub async fn process_ws(&mut self, msg: Message) {
         trace!("[Background] Received ws message {:?}", msg);
+        self.last_seen = Instant::now();
 
         let msg = match msg {
             Message::Text(msg) => msg,
@@ -131,7 +511,19 @@ impl BackgroundProcess {
                 self.pong().await?;
                 return;
             }
-            Message::Pong(_) => return,
+            Message::Pong(payload) => {
+                // Only the pong of the last ping measures the latency
+                match self.ping_sent {
+                    Some(sent) if payload == self.ping_seq.to_be_bytes() => {
+                        let latency = sent.elapsed();
+                        debug!("[Background] Pong after {:?}", latency);
+                        self.ping_sent = None;
+                        self.latency_tx.send_replace(Some(latency));
+                    }
+                    _ => {}
+                }
+                return;
+            }
             Message::Close(reason) => {
                 throw!(SolanaClientError::WsClosed(reason.map(|r| r.to_string())));
             }
@@ -140,42 +532,81 @@ impl BackgroundProcess {
             }
         };
 
+        // Responses of a batch request
+        if msg.trim_start().starts_with('[') {
+            match from_str::<Vec<Box<RawValue>>>(&msg) {
+                Ok(items) => {
+                    for item in items {
+                        self.process_text(item.get()).await?;
+                    }
+                }
+                Err(e) => warn!("Cannot deserialize ws message {}, errors: {:?}", msg, e),
+            }
+            return;
+        }
+
+        self.process_text(&msg).await?
+    }
+
+    #[throws(SolanaClientError)]
+    async fn process_text(&mut self, msg: &str) {
         let mut errors = vec![];
 
-        match from_str::<RpcNotification>(&msg) {
+        match from_str::<RpcNotification>(msg) {
             Ok(notif) => {
-                if self.sub_tx.send(Ok(notif)).is_err() {
-                    throw!(SolanaClientError::SubscriptionDropped)
+                let server_id = notif.params.subscription;
+                match self.server_ids.get(&server_id) {
+                    Some(&subid) => self.route(subid, notif).await?,
+                    None if self.subscribing() => {
+                        debug!("Holding notification for server id: {}", server_id);
+                        let early = self.early_notifs.entry(server_id).or_default();
+                        if early.len() == EARLY_NOTIFICATION_LIMIT {
+                            early.pop_front();
+                        }
+                        early.push_back(notif);
+                    }
+                    None => debug!("Subscription for server id: {} not found", server_id),
                 }
                 return;
             }
             Err(e) => errors.push(e),
         }
 
-        match from_str::<RpcResponse>(&msg) {
+        match from_str::<RpcResponse>(msg) {
             Ok(resp) => {
-                let id = resp.id;
-                if let Some(responder) = self.pendings.remove(&id) {
-                    if responder.send(Ok(resp)).is_err() {
-                        warn!("Responder for req: {} droppped", id);
-                    }
-                } else {
-                    warn!("Responder for req: {} not found", id);
+                self.process_resp(resp).await?;
+                if !self.subscribing() {
+                    self.early_notifs.clear();
                 }
                 return;
             }
             Err(e) => errors.push(e),
         }
 
-        match from_str::<RpcError>(&msg) {
+        match from_str::<RpcError>(msg) {
             Ok(error) => {
                 let id = error.id;
-                if let Some(responder) = self.pendings.remove(&id) {
-                    if responder.send(Err(error)).is_err() {
-                        warn!("Responder for req: {} droppped", id);
+                match self.pendings.remove(&id) {
+                    Some(Pending::Request(Request { responder, .. })) => {
+                        if responder.send(Err(error.into())).is_err() {
+                            warn!("Responder for req: {} droppped", id);
+                        }
+                    }
+                    Some(Pending::Resubscribe { subid, .. }) => {
+                        error!(
+                            "Resubscribing subscription {} failed: {}",
+                            subid, error.error.message
+                        );
+                        if let Some(LiveSubscription {
+                            notifier: Some(notifier),
+                            ..
+                        }) = self.subscriptions.remove(&subid)
+                        {
+                            notifier.send(Err(error.into())).await;
+                        }
                     }
-                } else {
-                    warn!("Responder for req: {} not found", id);
+                    Some(Pending::Cleanup) => {}
+                    None => warn!("Responder for req: {} not found", id),
                 }
                 return;
             }
@@ -190,24 +621,360 @@ impl BackgroundProcess {
     }
 
     #[throws(SolanaClientError)]
-    pub async fn process_req(&mut self, rr: (String, Box<RawValue>, Responder)) {
-        trace!("[Background] Received request {:?}", rr);
+    async fn process_resp(&mut self, mut resp: RpcResponse) {
+        let id = resp.id;
+        match self.pendings.remove(&id) {
+            Some(Pending::Request(Request {
+                method,
+                params,
+                responder,
+                notifier,
+                watchdog,
+            })) => {
+                if method.ends_with("Subscribe") {
+                    if let Ok(server_id) = from_str::<u64>(resp.result.get()) {
+                        let cancelled = responder.is_closed()
+                            || matches!(&notifier, Some(notifier) if notifier.is_closed());
+                        if cancelled {
+                            // The subscribe call is cancelled, timed out or dropped before
+                            // getting the response, nobody would receive the notifications
+                            self.unsubscribe_server(&method, server_id).await?;
+                            return;
+                        }
+
+                        let subid = self.subid();
+                        self.subscriptions.insert(
+                            subid,
+                            LiveSubscription {
+                                method,
+                                params,
+                                server_id: Some(server_id),
+                                notifier,
+                                watchdog,
+                                last_notif: Instant::now(),
+                            },
+                        );
+                        self.arm_watchdog(watchdog);
+                        self.server_ids.insert(server_id, subid);
+                        resp.result = RawValue::from_string(subid.to_string()).unwrap();
+
+                        if responder.send(Ok(resp)).is_err() {
+                            warn!("Responder for req: {} droppped", id);
+                        }
+                        self.flush_early(server_id, subid).await?;
+                        return;
+                    }
+                }
+
+                if responder.send(Ok(resp)).is_err() {
+                    warn!("Responder for req: {} droppped", id);
+                }
+            }
+            Some(Pending::Resubscribe { subid, method }) => {
+                let server_id = match from_str::<u64>(resp.result.get()) {
+                    Ok(server_id) => server_id,
+                    Err(e) => {
+                        error!("Cannot parse resubscribed id for {}: {}", subid, e);
+                        return;
+                    }
+                };
 
-        let (method, params, responder) = rr;
+                match self.subscriptions.get_mut(&subid) {
+                    Some(sub) => {
+                        debug!("Subscription {} resubscribed as {}", subid, server_id);
+                        sub.server_id = Some(server_id);
+                        sub.last_notif = Instant::now();
+                        let watchdog = sub.watchdog;
+                        self.server_ids.insert(server_id, subid);
+                        self.arm_watchdog(watchdog);
+                        self.flush_early(server_id, subid).await?;
+                    }
+                    None => {
+                        // Unsubscribed by the client while resubscribing
+                        self.unsubscribe_server(&method, server_id).await?;
+                    }
+                }
+            }
+            Some(Pending::Cleanup) => {}
+            None => warn!("Responder for req: {} not found", id),
+        }
+    }
+
+    /// Deliver a notification to its subscription, or to `Client::recv` if the
+    /// subscription is not made through `Client::subscribe`.
+    #[throws(SolanaClientError)]
+    async fn route(&mut self, subid: u64, mut notif: RpcNotification) {
+        let mut finished = false;
+        if let Some(sub) = self.subscriptions.get_mut(&subid) {
+            sub.last_notif = Instant::now();
+            finished = sub.method == "signatureSubscribe" && is_final_signature(&notif);
+        }
+
+        match self
+            .subscriptions
+            .get(&subid)
+            .and_then(|sub| sub.notifier.as_ref())
+        {
+            Some(notifier) => {
+                let blocks = notifier.blocks();
+                let start = Instant::now();
+                let item = Ok(notif.params.result);
+                if !notifier.send_at(notif.received, item).await {
+                    debug!("Subscription {} receiver dropped", subid);
+                }
+                // Nothing is read while waiting for a slow consumer, which
+                // must not pass for a dead connection or silent subscriptions
+                if blocks {
+                    self.pause_clocks(start.elapsed());
+                }
+            }
+            None => {
+                notif.params.subscription = subid;
+                if self.sub_tx.send(Ok(notif)).is_err() {
+                    throw!(SolanaClientError::SubscriptionDropped)
+                }
+            }
+        }
+
+        // The server cancels the signature subscription after its final notification,
+        // so it is neither replayed nor unsubscribed. This also ends the stream.
+        if finished {
+            debug!("[Background] Signature subscription {} finished", subid);
+            if let Some(server_id) = self
+                .subscriptions
+                .remove(&subid)
+                .and_then(|sub| sub.server_id)
+            {
+                self.server_ids.remove(&server_id);
+            }
+        }
+    }
+
+    /// Push the stale and watchdog deadlines back by the time the connection was not read.
+    fn pause_clocks(&mut self, paused: Duration) {
+        self.last_seen += paused;
+        if let Some(ping_sent) = &mut self.ping_sent {
+            *ping_sent += paused;
+        }
+        for sub in self.subscriptions.values_mut() {
+            sub.last_notif += paused;
+        }
+        if let Some(deadline) = &mut self.watchdog_deadline {
+            *deadline += paused;
+        }
+    }
+
+    /// Make sure the watchdog deadline is no later than a full cadence from now.
+    fn arm_watchdog(&mut self, watchdog: Option<Watchdog>) {
+        if let Some(watchdog) = watchdog {
+            let deadline = Instant::now() + watchdog.cadence;
+            self.watchdog_deadline = Some(match self.watchdog_deadline {
+                Some(current) => current.min(deadline),
+                None => deadline,
+            });
+        }
+    }
+
+    /// Raise a stall for the confirmed subscriptions silent for longer than their
+    /// cadence and act on it, then compute the next deadline.
+    #[throws(SolanaClientError)]
+    async fn check_watchdogs(&mut self) {
+        let now = Instant::now();
+        let mut stalled = vec![];
+        let mut deadline: Option<Instant> = None;
+
+        for (&subid, sub) in &mut self.subscriptions {
+            let watchdog = match sub.watchdog {
+                Some(watchdog) if sub.server_id.is_some() => watchdog,
+                _ => continue,
+            };
+            if now >= sub.last_notif + watchdog.cadence {
+                stalled.push((subid, watchdog));
+                sub.last_notif = now;
+            }
+            let next = sub.last_notif + watchdog.cadence;
+            deadline = Some(deadline.map_or(next, |deadline| deadline.min(next)));
+        }
+        self.watchdog_deadline = deadline;
+
+        stalled.sort_unstable_by_key(|(subid, _)| *subid);
+        let mut reconnect = None;
+        for (subid, watchdog) in stalled {
+            warn!(
+                "[Background] Subscription {} received nothing for {:?}",
+                subid, watchdog.cadence
+            );
+            if let Some(notifier) = &self.subscriptions[&subid].notifier {
+                notifier
+                    .send(Err(SolanaClientError::Stalled(watchdog.cadence)))
+                    .await;
+            }
+
+            match watchdog.on_stall {
+                OnStall::Notify => {}
+                OnStall::Resubscribe => self.resubscribe(subid).await?,
+                OnStall::Reconnect => reconnect = Some(watchdog.cadence),
+            }
+        }
+
+        if let Some(cadence) = reconnect {
+            throw!(SolanaClientError::Stalled(cadence));
+        }
+    }
+
+    /// Unsubscribe a live subscription on the server and subscribe it again,
+    /// keeping its id.
+    #[throws(SolanaClientError)]
+    async fn resubscribe(&mut self, subid: u64) {
+        let id = self.id();
+        let sub = match self.subscriptions.get_mut(&subid) {
+            Some(sub) => sub,
+            None => return,
+        };
+        let server_id = sub.server_id.take();
+        let method = sub.method.clone();
+        let req = RpcRequest::new(id, &method, sub.params.clone());
+
+        if let Some(server_id) = server_id {
+            self.server_ids.remove(&server_id);
+            self.unsubscribe_server(&method, server_id).await?;
+        }
+
+        debug!("[Background] Resubscribing {} with id {}", subid, id);
+        self.pendings
+            .insert(id, Pending::Resubscribe { subid, method });
+        self.ws.send(Message::Text(to_string(&req)?)).await?;
+    }
+
+    /// Deliver the notifications held before the subscription id is confirmed.
+    #[throws(SolanaClientError)]
+    async fn flush_early(&mut self, server_id: u64, subid: u64) {
+        for notif in self.early_notifs.remove(&server_id).unwrap_or_default() {
+            self.route(subid, notif).await?;
+        }
+    }
+
+    /// Whether any subscribe request is waiting for its response.
+    fn subscribing(&self) -> bool {
+        self.pendings.values().any(|pending| match pending {
+            Pending::Request(req) => req.method.ends_with("Subscribe"),
+            Pending::Resubscribe { .. } => true,
+            Pending::Cleanup => false,
+        })
+    }
+
+    /// Unsubscribe a subscription whose handle is dropped.
+    #[throws(SolanaClientError)]
+    async fn unsubscribe(&mut self, subid: u64) {
+        let sub = match self.subscriptions.remove(&subid) {
+            Some(sub) => sub,
+            None => return, // Already unsubscribed
+        };
+        debug!("[Background] Subscription {} dropped", subid);
+
+        // Without a server id, the server side subscription is dropped
+        // once the resubscribe response arrives.
+        if let Some(server_id) = sub.server_id {
+            self.server_ids.remove(&server_id);
+            self.unsubscribe_server(&sub.method, server_id).await?;
+        }
+    }
+
+    /// Send the unsubscribe request matching the subscribe method, ignoring its response.
+    #[throws(SolanaClientError)]
+    async fn unsubscribe_server(&mut self, method: &str, server_id: u64) {
+        self.early_notifs.remove(&server_id);
+
+        let id = self.id();
+        let method = method.replace("Subscribe", "Unsubscribe");
+        let req = RpcRequest::new(id, &method, [server_id]);
+        self.pendings.insert(id, Pending::Cleanup);
+        self.ws.send(Message::Text(to_string(&req)?)).await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn process_req(&mut self, reqs: Vec<Request>) {
+        trace!("[Background] Received requests {:?}", reqs);
+
+        let mut msgs = vec![];
+        for req in reqs {
+            msgs.extend(self.prepare_req(req)?);
+        }
+
+        // A single request is sent as is, multiple ones as a batch
+        let msg = match msgs.as_slice() {
+            [] => return,
+            [msg] => to_string(msg)?,
+            msgs => to_string(msgs)?,
+        };
+
+        self.ws.send(Message::Text(msg)).await?
+    }
+
+    /// Register the request as pending and build its message, or answer it
+    /// locally if there is nothing to send.
+    #[throws(SolanaClientError)]
+    fn prepare_req(&mut self, mut req: Request) -> Option<RpcRequest> {
         let id = self.id();
-        let req = RpcRequest::new(id, &method, params);
-        let exist = self.pendings.insert(id, responder);
+
+        if req.method.ends_with("Unsubscribe") {
+            if let Ok((subid,)) = from_str::<(u64,)>(req.params.get()) {
+                match self.subscriptions.remove(&subid) {
+                    Some(LiveSubscription {
+                        server_id: Some(server_id),
+                        ..
+                    }) => {
+                        self.server_ids.remove(&server_id);
+                        req.params = RawValue::from_string(to_string(&[server_id])?)?;
+                    }
+                    Some(LiveSubscription {
+                        server_id: None, ..
+                    }) => {
+                        // Still being resubscribed, the server side subscription
+                        // is dropped once the resubscribe response arrives.
+                        answer(req.responder, id, "true");
+                        return None;
+                    }
+                    None => {
+                        // Not a subscription id handed out by this client, nor a server id
+                        debug!("[Background] Unsubscribing unknown subscription {}", subid);
+                        answer(req.responder, id, "false");
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let msg = RpcRequest::new(id, &req.method, req.params.clone());
+        let exist = self.pendings.insert(id, Pending::Request(req));
         if exist.is_some() {
             error!("ReqId {} exists", id);
         }
 
-        self.ws.send(Message::Text(to_string(&req)?)).await?
+        Some(msg)
     }
 
     pub fn id(&mut self) -> u64 {
         self.reqid += 1;
         self.reqid
     }
+
+    pub fn subid(&mut self) -> u64 {
+        self.subid += 1;
+        self.subid
+    }
+}
+
+/// Answer a request locally, without sending it to the server.
+fn answer(responder: Responder, id: u64, result: &str) {
+    let resp = RpcResponse {
+        jsonrpc: "2.0".into(),
+        id,
+        result: RawValue::from_string(result.into()).unwrap(),
+    };
+    if responder.send(Ok(resp)).is_err() {
+        warn!("Responder for req: {} droppped", id);
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -216,3 +983,14 @@ pub enum Either<T, U> {
     Left(T),
     Right(U),
 }
+
+/// Whether the signature notification is the processed one, rather than the received one.
+fn is_final_signature(notif: &RpcNotification) -> bool {
+    matches!(
+        from_str::<Response<RpcSignatureResult>>(notif.params.result.get()),
+        Ok(Response {
+            value: RpcSignatureResult::ProcessedSignature(_),
+            ..
+        })
+    )
+}
@@ -0,0 +1,195 @@
+use crate::errors::Result as MyResult;
+use futures::task::{Context, Poll, Waker};
+use serde_json::value::RawValue;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+type Item = MyResult<Box<RawValue>>;
+
+/// What to do when a subscription receives notifications faster than they are consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Queue every notification.
+    #[default]
+    Unbounded,
+    /// Wait for the consumer when the queue is full. This stalls the whole connection,
+    /// the time spent waiting does not count towards the stale timeout and the watchdogs.
+    Block(usize),
+    /// Drop the oldest queued notification when the queue is full.
+    DropOldest(usize),
+    /// Drop the incoming notification when the queue is full.
+    DropNewest(usize),
+    /// Keep only the latest notification, useful when only the newest state matters.
+    CoalesceLatest,
+}
+
+struct State {
+    // With the time each item was received.
+    queue: VecDeque<(Instant, Item)>,
+    rx_waker: Option<Waker>,
+    tx_closed: bool,
+    rx_closed: bool,
+}
+
+struct Shared {
+    backpressure: Backpressure,
+    state: Mutex<State>,
+    space: Notify,
+    dropped: AtomicU64,
+}
+
+/// The sending half held by the background process.
+#[derive(Debug)]
+pub(crate) struct Notifier {
+    shared: Arc<Shared>,
+}
+
+/// The receiving half held by the subscription.
+pub(crate) struct Receiver {
+    shared: Arc<Shared>,
+}
+
+pub(crate) fn channel(backpressure: Backpressure) -> (Notifier, Receiver) {
+    let shared = Arc::new(Shared {
+        backpressure,
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            rx_waker: None,
+            tx_closed: false,
+            rx_closed: false,
+        }),
+        space: Notify::new(),
+        dropped: AtomicU64::new(0),
+    });
+
+    (
+        Notifier {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared")
+            .field("backpressure", &self.backpressure)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
+}
+
+impl Notifier {
+    /// Queue an item received now, see `send_at`.
+    pub(crate) async fn send(&self, item: Item) -> bool {
+        self.send_at(Instant::now(), item).await
+    }
+
+    /// Queue a notification received at `received` following the backpressure policy.
+    /// Errors are always queued. Returns false if the receiver is dropped.
+    pub(crate) async fn send_at(&self, received: Instant, item: Item) -> bool {
+        loop {
+            let space = self.shared.space.notified();
+
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if state.rx_closed {
+                    return false;
+                }
+
+                let full = match self.shared.backpressure {
+                    Backpressure::Unbounded => false,
+                    Backpressure::Block(cap)
+                    | Backpressure::DropOldest(cap)
+                    | Backpressure::DropNewest(cap) => state.queue.len() >= cap.max(1),
+                    Backpressure::CoalesceLatest => !state.queue.is_empty(),
+                };
+
+                if !full || item.is_err() {
+                    state.push(received, item);
+                    return true;
+                }
+
+                match self.shared.backpressure {
+                    Backpressure::DropOldest(_) | Backpressure::CoalesceLatest => {
+                        // Keep the queued errors, drop the oldest notification
+                        if let Some(i) = state.queue.iter().position(|(_, item)| item.is_ok()) {
+                            state.queue.remove(i);
+                            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        state.push(received, item);
+                        return true;
+                    }
+                    Backpressure::DropNewest(_) => {
+                        self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        return true;
+                    }
+                    Backpressure::Block(_) | Backpressure::Unbounded => {}
+                }
+            }
+
+            space.await;
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap().rx_closed
+    }
+
+    /// Whether `send` may wait for the consumer.
+    pub(crate) fn blocks(&self) -> bool {
+        matches!(self.shared.backpressure, Backpressure::Block(_))
+    }
+}
+
+impl State {
+    fn push(&mut self, received: Instant, item: Item) {
+        self.queue.push_back((received, item));
+        if let Some(waker) = self.rx_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.tx_closed = true;
+        if let Some(waker) = state.rx_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Receiver {
+    /// The next item with the time it was received.
+    pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<(Instant, Item)>> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if let Some(item) = state.queue.pop_front() {
+            self.shared.space.notify_one();
+            return Poll::Ready(Some(item));
+        }
+
+        if state.tx_closed {
+            return Poll::Ready(None);
+        }
+
+        state.rx_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().rx_closed = true;
+        self.shared.space.notify_one();
+    }
+}
@@ -0,0 +1,145 @@
+use crate::client::Client;
+use crate::errors::SolanaClientError;
+use crate::http_client::HttpClient;
+use crate::subscription::Subscription;
+use fehler::throws;
+use futures::StreamExt;
+use log::{debug, warn};
+use serde_json::json;
+use solana_client::rpc_config::RpcContextConfig;
+use solana_client::rpc_response::{Response, RpcBlockhash, SlotInfo};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use tokio::{select, spawn};
+
+/// A recent blockhash with the block heights to tell when it expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatestBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+    /// The block height when the blockhash was fetched.
+    pub block_height: u64,
+    /// The slot the blockhash was fetched at.
+    pub slot: Slot,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BlockhashTrackerConfig {
+    pub commitment: CommitmentConfig,
+    /// Refresh on a new slot, at most this often.
+    pub min_refresh: Duration,
+    /// Refresh at least this often, even when no slot arrives.
+    pub max_refresh: Duration,
+}
+
+impl Default for BlockhashTrackerConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            min_refresh: Duration::from_secs(2),
+            max_refresh: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Keeps a fresh blockhash in the background, refreshed over HTTP as new slots
+/// arrive over the websocket. Cloning it is cheap and reading it does not wait.
+/// The background task stops once every clone is dropped.
+#[derive(Debug, Clone)]
+pub struct BlockhashTracker {
+    rx: watch::Receiver<LatestBlockhash>,
+}
+
+impl BlockhashTracker {
+    /// Fetch the first blockhash and start tracking.
+    #[throws(SolanaClientError)]
+    pub async fn spawn(client: &Client, http: &HttpClient, config: BlockhashTrackerConfig) -> Self {
+        let slots = client.slot_subscribe().await?;
+        let latest = fetch(http, config.commitment).await?;
+        let (tx, rx) = watch::channel(latest);
+
+        spawn(track(tx, slots, http.clone(), config));
+        Self { rx }
+    }
+
+    pub fn latest(&self) -> LatestBlockhash {
+        *self.rx.borrow()
+    }
+
+    /// A receiver notified on every refresh.
+    pub fn watch(&self) -> watch::Receiver<LatestBlockhash> {
+        self.rx.clone()
+    }
+}
+
+async fn track(
+    tx: watch::Sender<LatestBlockhash>,
+    mut slots: Subscription<SlotInfo>,
+    http: HttpClient,
+    config: BlockhashTrackerConfig,
+) {
+    let mut timer = interval(config.max_refresh);
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    timer.tick().await;
+    let mut last_refresh = Instant::now();
+    let mut slots_closed = false;
+
+    loop {
+        select! {
+            _ = tx.closed() => break,
+            _ = timer.tick() => {}
+            slot = slots.next(), if !slots_closed => match slot {
+                Some(Ok(_)) if last_refresh.elapsed() >= config.min_refresh => {}
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    warn!("[Blockhash] Slot subscription failed: {}", e);
+                    continue;
+                }
+                None => {
+                    // Only the periodic refresh is left
+                    slots_closed = true;
+                    continue;
+                }
+            },
+        }
+
+        match fetch(&http, config.commitment).await {
+            Ok(latest) => {
+                debug!("[Blockhash] Refreshed {:?}", latest);
+                tx.send_replace(latest);
+            }
+            Err(e) => warn!("[Blockhash] Cannot refresh: {}", e),
+        }
+        last_refresh = Instant::now();
+        timer.reset();
+    }
+}
+
+/// Fetch the blockhash and the block height in a single batch.
+#[throws(SolanaClientError)]
+async fn fetch(http: &HttpClient, commitment: CommitmentConfig) -> LatestBlockhash {
+    let context = RpcContextConfig {
+        commitment: Some(commitment),
+        min_context_slot: None,
+    };
+
+    let mut batch = http.batch();
+    let blockhash =
+        batch.request::<_, Response<RpcBlockhash>>("getLatestBlockhash", &json! {[commitment]})?;
+    let block_height = batch.request::<_, u64>("getBlockHeight", &json! {[context]})?;
+    batch.send().await?;
+
+    let blockhash = blockhash.await?;
+    LatestBlockhash {
+        blockhash: Hash::from_str(&blockhash.value.blockhash)
+            .map_err(|e| SolanaClientError::Upstream(format!("Invalid blockhash: {}", e)))?,
+        last_valid_block_height: blockhash.value.last_valid_block_height,
+        block_height: block_height.await?,
+        slot: blockhash.context.slot,
+    }
+}
@@ -1,42 +1,86 @@
 use crate::{
-    background::BackgroundProcess,
+    background::{BackgroundProcess, Control, Request},
+    backpressure::{self, Backpressure, Notifier},
     errors::{Result as MyResult, SolanaClientError},
-    rpc_message::{RpcError, RpcNotification, RpcResponse},
-    Responder,
+    http_client::HttpClient,
+    reconnect::ReconnectPolicy,
+    redundant::RedundantClient,
+    rpc_message::{RpcNotification, RpcResponse},
+    subscription::Subscription,
+    watchdog::Watchdog,
+    WsStream,
 };
 use fehler::{throw, throws};
 use futures::{
     task::{Context, Poll},
-    Future,
+    Future, StreamExt,
 };
-use http::request::Request;
-use log::trace;
+use http::request::Request as HttpRequest;
+use log::{trace, warn};
 use paste::paste;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::value::{RawValue, Value};
 use serde_json::{from_str, json, to_string};
+use solana_account_decoder::UiAccount;
 use solana_client::rpc_config::{
     RpcAccountInfoConfig, RpcBlockSubscribeConfig, RpcBlockSubscribeFilter,
     RpcProgramAccountsConfig, RpcSignatureSubscribeConfig, RpcTransactionLogsConfig,
     RpcTransactionLogsFilter,
 };
+use solana_client::rpc_response::{
+    Response, RpcBlockUpdate, RpcKeyedAccount, RpcLogsResponse, RpcSignatureResult, RpcVote,
+    SlotInfo, SlotUpdate,
+};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use tokio::sync::{broadcast, mpsc, oneshot};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{self, sleep, Sleep};
 use tokio_tungstenite::connect_async;
 use tungstenite::handshake::client::generate_key;
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
 use url::Url;
 
 #[derive(Default, Debug, Clone)]
 pub struct ClientBuilder {
-    headers: HashMap<String, String>,
-    url: Option<String>,
-    ws_url: Option<String>,
-    ping_every: Option<u64>,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) url: Option<String>,
+    pub(crate) ws_url: Option<String>,
+    pub(crate) ping_every: Option<u64>,
+    pub(crate) stale_timeout: Option<Duration>,
+    pub(crate) reconnect_policy: ReconnectPolicy,
+    pub(crate) backpressure: Backpressure,
+    pub(crate) request_timeout: Option<Duration>,
+    pub(crate) failovers: Vec<Endpoint>,
+}
+
+/// A websocket endpoint to fail over to, with its own headers.
+#[derive(Default, Debug, Clone)]
+pub struct Endpoint {
+    pub(crate) ws_url: String,
+    pub(crate) headers: HashMap<String, String>,
+}
+
+impl Endpoint {
+    pub fn new(ws_url: &str) -> Self {
+        Endpoint {
+            ws_url: ws_url.into(),
+            headers: HashMap::new(),
+        }
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) -> &mut Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
 }
 
 impl ClientBuilder {
@@ -64,52 +108,283 @@ impl ClientBuilder {
         self
     }
 
+    /// Treat the connection as dead when nothing, not even a pong, is received
+    /// for this long. Checked on every ping, defaults to twice the ping interval.
+    pub fn stale_timeout(&mut self, stale_timeout: Duration) -> &mut Self {
+        self.stale_timeout = Some(stale_timeout);
+        self
+    }
+
+    pub fn reconnect_policy(&mut self, reconnect_policy: ReconnectPolicy) -> &mut Self {
+        self.reconnect_policy = reconnect_policy;
+        self
+    }
+
+    /// Add an endpoint to fail over to, after `ws_url` and the endpoints added before.
+    /// The builder headers are sent to every endpoint, unless overridden by the endpoint.
+    /// The background process moves to the next endpoint when the current one closes
+    /// or stops answering pings, replaying the subscriptions there.
+    pub fn failover(&mut self, endpoint: &Endpoint) -> &mut Self {
+        self.failovers.push(endpoint.clone());
+        self
+    }
+
+    /// The default backpressure policy of the subscriptions.
+    pub fn backpressure(&mut self, backpressure: Backpressure) -> &mut Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// The default timeout of the requests, requests wait forever if not set.
+    pub fn request_timeout(&mut self, request_timeout: Duration) -> &mut Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
     #[throws(SolanaClientError)]
     pub async fn build(&mut self) -> Client {
-        let ws_url = self.ws_url.as_ref().unwrap();
-        let url = Url::parse(ws_url)?;
-        let host = url.host_str().ok_or(SolanaClientError::NoHostName)?;
-
-        let mut builder = Request::builder()
-            .method("GET")
-            .header("Host", host)
-            .header("Connection", "Upgrade")
-            .header("Upgrade", "websocket")
-            .header("Sec-WebSocket-Version", "13")
-            .header("Sec-WebSocket-Key", generate_key())
-            .uri(ws_url);
-
-        for (key, value) in &self.headers {
-            builder = builder.header(key, value);
+        let (endpoint, stream) = self.connect(0).await?;
+
+        let (bp, sub_rx, req_tx, control_tx, closed_rx, latency_rx) =
+            BackgroundProcess::new(stream, endpoint, self.clone());
+        let task = bp.start();
+
+        Client {
+            task: Arc::new(Mutex::new(Some(task))),
+            req_tx,
+            sub_rx,
+            control_tx,
+            closed_rx,
+            latency_rx,
+            backpressure: self.backpressure,
+            request_timeout: self.request_timeout,
+            watchdog: None,
+            monotonic_slots: false,
         }
+    }
 
-        let (stream, _) = connect_async(builder.body(())?).await?;
+    /// Connect to every endpoint at once, the `ws_url` and the failovers, to
+    /// subscribe to the same feeds on all of them. Each endpoint reconnects on its own,
+    /// the endpoints down at startup are connected in the background following the
+    /// reconnect policy. Fails only if no endpoint can be connected.
+    #[throws(SolanaClientError)]
+    pub async fn build_redundant(&self) -> RedundantClient {
+        let endpoints = self.endpoints();
+        if endpoints.is_empty() {
+            throw!(SolanaClientError::NoUrl);
+        }
+
+        let builders = endpoints
+            .into_iter()
+            .map(|endpoint| {
+                let mut builder = self.clone();
+                builder.ws_url = Some(endpoint.ws_url);
+                builder.headers = endpoint.headers;
+                builder.failovers.clear();
+                builder
+            })
+            .collect();
+        RedundantClient::connect(builders).await?
+    }
+
+    /// Build a JSON-RPC client over HTTP with the configured url and headers.
+    #[throws(SolanaClientError)]
+    pub fn build_http(&self) -> HttpClient {
+        let url = self.url.as_ref().ok_or(SolanaClientError::NoUrl)?;
+        HttpClient::new(url, &self.headers, self.request_timeout)?
+    }
 
-        let (bp, sub_rx, req_tx) = BackgroundProcess::new(stream, self.ping_every.unwrap_or(5));
-        bp.start();
+    pub(crate) fn ping_interval(&self) -> Duration {
+        Duration::from_secs(self.ping_every.unwrap_or(5))
+    }
 
-        Client { req_tx, sub_rx }
+    pub(crate) fn stale_after(&self) -> Duration {
+        self.stale_timeout.unwrap_or(self.ping_interval() * 2)
     }
+
+    /// The websocket endpoints in failover order.
+    pub(crate) fn endpoints(&self) -> Vec<Endpoint> {
+        let primary = self.ws_url.iter().map(|ws_url| Endpoint::new(ws_url));
+        primary
+            .chain(self.failovers.iter().cloned())
+            .map(|endpoint| {
+                let mut headers = self.headers.clone();
+                headers.extend(endpoint.headers);
+                Endpoint {
+                    headers,
+                    ..endpoint
+                }
+            })
+            .collect()
+    }
+
+    /// Open a websocket connection, trying the endpoints in order starting from `start`
+    /// and wrapping around. Returns the index of the connected endpoint, or the last error.
+    /// Also used by the background process to reconnect.
+    pub(crate) async fn connect(&self, start: usize) -> MyResult<(usize, WsStream)> {
+        let endpoints = self.endpoints();
+        let mut error = SolanaClientError::NoUrl;
+
+        for i in 0..endpoints.len() {
+            let i = (start + i) % endpoints.len();
+            match connect_endpoint(&endpoints[i]).await {
+                Ok(stream) => return Ok((i, stream)),
+                Err(e) => {
+                    warn!("[Client] Cannot connect to {}: {}", endpoints[i].ws_url, e);
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+}
+
+#[throws(SolanaClientError)]
+async fn connect_endpoint(endpoint: &Endpoint) -> WsStream {
+    let ws_url = &endpoint.ws_url;
+    let url = Url::parse(ws_url)?;
+    let host = url.host_str().ok_or(SolanaClientError::NoHostName)?;
+
+    let mut builder = HttpRequest::builder()
+        .method("GET")
+        .header("Host", host)
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", generate_key())
+        .uri(ws_url);
+
+    for (key, value) in &endpoint.headers {
+        builder = builder.header(key, value);
+    }
+
+    let (stream, _) = connect_async(builder.body(())?).await?;
+    stream
 }
 
 macro_rules! unsubscribe_method {
     ($meth:ident) => {
+        unsubscribe_method!($meth, concat!(stringify!($meth), "Unsubscribe"));
+    };
+    ($meth:ident, $method:expr) => {
         paste! {
             #[throws(SolanaClientError)]
-            pub async fn [<$meth _unsubscribe>](&mut self, subscription_id: u64) -> ResponseAwaiter<bool> {
-                let awaiter = self.request(concat!(stringify!($meth), "Unsubscribe"), &[subscription_id]).await?;
+            pub async fn [<$meth _unsubscribe>](&self, subscription_id: u64) -> ResponseAwaiter<bool> {
+                let awaiter = self.request($method, &[subscription_id]).await?;
                 awaiter
             }
         }
     };
 }
 
+/// A handle to the background process. Cloning it is cheap and the clones
+/// share the same connection, each with its own `recv` stream.
 pub struct Client {
-    req_tx: mpsc::Sender<(String, Box<RawValue>, Responder)>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    req_tx: mpsc::Sender<Vec<Request>>,
     sub_rx: broadcast::Receiver<MyResult<RpcNotification>>,
+    control_tx: mpsc::UnboundedSender<Control>,
+    closed_rx: watch::Receiver<Option<SolanaClientError>>,
+    latency_rx: watch::Receiver<Option<Duration>>,
+    backpressure: Backpressure,
+    request_timeout: Option<Duration>,
+    watchdog: Option<Watchdog>,
+    monotonic_slots: bool,
+}
+
+impl Clone for Client {
+    fn clone(&self) -> Self {
+        Self {
+            task: self.task.clone(),
+            req_tx: self.req_tx.clone(),
+            sub_rx: self.sub_rx.resubscribe(),
+            control_tx: self.control_tx.clone(),
+            closed_rx: self.closed_rx.clone(),
+            latency_rx: self.latency_rx.clone(),
+            backpressure: self.backpressure,
+            request_timeout: self.request_timeout,
+            watchdog: self.watchdog,
+            monotonic_slots: self.monotonic_slots,
+        }
+    }
 }
 
 impl Client {
+    /// Take the handle of the background task, shared by every clone of the client.
+    /// `None` if already taken. The task exits after `shutdown`, a fatal error, or
+    /// once every handle and subscription is dropped.
+    pub fn join_handle(&self) -> Option<JoinHandle<()>> {
+        self.task.lock().unwrap().take()
+    }
+
+    /// Resolves with the error the background process exits with.
+    pub async fn closed(&self) -> SolanaClientError {
+        let mut closed_rx = self.closed_rx.clone();
+        loop {
+            if let Some(e) = closed_rx.borrow().clone() {
+                return e;
+            }
+            if closed_rx.changed().await.is_err() {
+                return closed_rx
+                    .borrow()
+                    .clone()
+                    .unwrap_or(SolanaClientError::BackgroundProcessExited);
+            }
+        }
+    }
+
+    /// Round-trip time of the last answered ping, `None` until the first pong
+    /// of the current connection.
+    pub fn latency(&self) -> Option<Duration> {
+        *self.latency_rx.borrow()
+    }
+
+    /// Unsubscribe everything, close the websocket with the given code and reason,
+    /// and wait up to `timeout` for the server to acknowledge. Afterwards pending
+    /// requests fail with `Shutdown` and subscription streams end.
+    #[throws(SolanaClientError)]
+    pub async fn shutdown(&self, code: CloseCode, reason: &str, timeout: Duration) {
+        let (done, done_rx) = oneshot::channel();
+        let frame = CloseFrame {
+            code,
+            reason: reason.to_string().into(),
+        };
+        self.control_tx
+            .send(Control::Shutdown {
+                frame,
+                timeout,
+                done,
+            })
+            .map_err(|_| SolanaClientError::BackgroundProcessExited)?;
+        done_rx
+            .await
+            .map_err(|_| SolanaClientError::BackgroundProcessExited)?;
+    }
+
+    /// A handle whose subscriptions use the given backpressure policy.
+    pub fn with_backpressure(&self, backpressure: Backpressure) -> Self {
+        let mut client = self.clone();
+        client.backpressure = backpressure;
+        client
+    }
+
+    /// A handle whose subscriptions expect notifications at the watchdog cadence.
+    pub fn with_watchdog(&self, watchdog: Option<Watchdog>) -> Self {
+        let mut client = self.clone();
+        client.watchdog = watchdog;
+        client
+    }
+
+    /// A handle whose subscriptions drop the notifications with a context slot older
+    /// than the last one delivered, per pubkey for program subscriptions.
+    /// Notifications without a context slot are always delivered.
+    pub fn with_monotonic_slots(&self, enabled: bool) -> Self {
+        let mut client = self.clone();
+        client.monotonic_slots = enabled;
+        client
+    }
+
     #[throws(SolanaClientError)]
     pub async fn recv<T>(&mut self) -> (u64, T)
     where
@@ -123,123 +398,318 @@ impl Client {
 
     #[throws(SolanaClientError)]
     pub async fn recv_raw(&mut self) -> (u64, Box<RawValue>) {
-        let notif = self.sub_rx.recv().await??;
+        let notif = match self.sub_rx.recv().await {
+            Ok(notif) => notif?,
+            Err(broadcast::error::RecvError::Lagged(n)) => throw!(SolanaClientError::Lagged(n)),
+            Err(e) => throw!(e),
+        };
         (notif.params.subscription, notif.params.result)
     }
 
     #[throws(SolanaClientError)]
     pub async fn account_subscribe(
-        &mut self,
+        &self,
         pubkey: &Pubkey,
         config: Option<RpcAccountInfoConfig>,
-    ) -> ResponseAwaiter<u64> {
-        let awaiter = self
-            .request("accountSubscribe", &json! {[pubkey.to_string(), config]})
-            .await?;
-        awaiter
+    ) -> Subscription<Response<UiAccount>> {
+        self.subscribe("accountSubscribe", &json! {[pubkey.to_string(), config]})
+            .await?
     }
     unsubscribe_method!(account);
 
     #[throws(SolanaClientError)]
     pub async fn block_subscribe(
-        &mut self,
+        &self,
         filter: RpcBlockSubscribeFilter,
         config: Option<RpcBlockSubscribeConfig>,
-    ) -> ResponseAwaiter<u64> {
-        let awaiter = self
-            .request("blockSubscribe", &json! {[filter, config]})
-            .await?;
-        awaiter
+    ) -> Subscription<Response<RpcBlockUpdate>> {
+        self.subscribe("blockSubscribe", &json! {[filter, config]})
+            .await?
     }
     unsubscribe_method!(block);
 
     #[throws(SolanaClientError)]
     pub async fn logs_subscribe(
-        &mut self,
+        &self,
         filter: RpcTransactionLogsFilter,
         config: RpcTransactionLogsConfig,
-    ) -> ResponseAwaiter<u64> {
-        let awaiter = self
-            .request("logsSubscribe", &json! {[filter, config]})
-            .await?;
-        awaiter
+    ) -> Subscription<Response<RpcLogsResponse>> {
+        self.subscribe("logsSubscribe", &json! {[filter, config]})
+            .await?
     }
     unsubscribe_method!(logs);
 
     #[throws(SolanaClientError)]
     pub async fn program_subscribe(
-        &mut self,
+        &self,
         pubkey: &Pubkey,
         config: Option<RpcProgramAccountsConfig>,
-    ) -> ResponseAwaiter<u64> {
-        let awaiter = self
-            .request("programSubscribe", &json! {[pubkey.to_string(), config]})
-            .await?;
-        awaiter
+    ) -> Subscription<Response<RpcKeyedAccount>> {
+        self.subscribe("programSubscribe", &json! {[pubkey.to_string(), config]})
+            .await?
     }
     unsubscribe_method!(program);
 
     #[throws(SolanaClientError)]
-    pub async fn vote_subscribe(&mut self) -> ResponseAwaiter<u64> {
-        let awaiter = self.request("voteSubscribe", &Value::Null).await?;
-        awaiter
+    pub async fn vote_subscribe(&self) -> Subscription<RpcVote> {
+        self.subscribe("voteSubscribe", &Value::Null).await?
     }
     unsubscribe_method!(vote);
 
     #[throws(SolanaClientError)]
-    pub async fn root_subscribe(&mut self) -> ResponseAwaiter<u64> {
-        let awaiter = self.request("rootSubscribe", &Value::Null).await?;
-        awaiter
+    pub async fn root_subscribe(&self) -> Subscription<Slot> {
+        self.subscribe("rootSubscribe", &Value::Null).await?
     }
     unsubscribe_method!(root);
 
     #[throws(SolanaClientError)]
-    pub async fn slot_subscribe(&mut self) -> ResponseAwaiter<u64> {
-        let awaiter = self.request("slotSubscribe", &Value::Null).await?;
-        awaiter
+    pub async fn slot_subscribe(&self) -> Subscription<SlotInfo> {
+        self.subscribe("slotSubscribe", &Value::Null).await?
     }
     unsubscribe_method!(slot);
 
+    #[throws(SolanaClientError)]
+    pub async fn slots_updates_subscribe(&self) -> Subscription<SlotUpdate> {
+        self.subscribe("slotsUpdatesSubscribe", &Value::Null)
+            .await?
+    }
+    unsubscribe_method!(slots_updates, "slotsUpdatesUnsubscribe");
+
     #[throws(SolanaClientError)]
     pub async fn signature_subscribe(
-        &mut self,
+        &self,
         signature: &Signature,
         config: Option<RpcSignatureSubscribeConfig>,
-    ) -> ResponseAwaiter<u64> {
-        let awaiter = self
-            .request(
-                "signatureSubscribe",
-                &json! {[signature.to_string(), config]},
-            )
-            .await?;
-        awaiter
+    ) -> Subscription<Response<RpcSignatureResult>> {
+        self.subscribe(
+            "signatureSubscribe",
+            &json! {[signature.to_string(), config]},
+        )
+        .await?
     }
     unsubscribe_method!(signature);
 
+    /// Wait for the signature to reach the commitment, failing with `Timeout` after `timeout`.
     #[throws(SolanaClientError)]
-    pub async fn request<T, R>(&mut self, method: &str, params: &T) -> ResponseAwaiter<R>
+    pub async fn confirm_signature(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+        timeout: Duration,
+    ) -> RpcSignatureResult {
+        let config = RpcSignatureSubscribeConfig {
+            commitment: Some(commitment),
+            enable_received_notification: None,
+        };
+        self.confirm_signature_with_config(signature, config, timeout)
+            .await?
+    }
+
+    /// Resolve with the first signature notification, which is the received one
+    /// when `enable_received_notification` is set. The subscription is cleaned up
+    /// afterwards, the server already cancels it after the processed notification.
+    #[throws(SolanaClientError)]
+    pub async fn confirm_signature_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcSignatureSubscribeConfig,
+        timeout: Duration,
+    ) -> RpcSignatureResult {
+        let confirm = async {
+            let mut subscription = self.signature_subscribe(signature, Some(config)).await?;
+            match subscription.next().await {
+                Some(resp) => Ok(resp?.value),
+                None => Err(self
+                    .closed_rx
+                    .borrow()
+                    .clone()
+                    .unwrap_or(SolanaClientError::SubscriptionDropped)),
+            }
+        };
+
+        time::timeout(timeout, confirm)
+            .await
+            .map_err(|_| SolanaClientError::Timeout)??
+    }
+
+    /// Subscribe with an arbitrary method, the notifications are delivered
+    /// to the returned subscription instead of `recv`.
+    #[throws(SolanaClientError)]
+    pub async fn subscribe<T, R>(&self, method: &str, params: &T) -> Subscription<R>
     where
         T: Serialize,
     {
-        let params = to_string(params)?;
-        let params = RawValue::from_string(params)?;
+        let (notifier, rx) = backpressure::channel(self.backpressure);
+        let id = self.send(method, params, Some(notifier)).await?.await?;
+        Subscription::new(id, rx, self.control_tx.clone(), self.monotonic_slots)
+    }
 
-        let (tx, rx) = oneshot::channel();
+    #[throws(SolanaClientError)]
+    pub async fn request<T, R>(&self, method: &str, params: &T) -> ResponseAwaiter<R>
+    where
+        T: Serialize,
+    {
+        self.send(method, params, None).await?
+    }
+
+    /// A handle whose requests and subscribe calls use the given timeout.
+    pub fn with_request_timeout(&self, request_timeout: Option<Duration>) -> Self {
+        let mut client = self.clone();
+        client.request_timeout = request_timeout;
+        client
+    }
 
-        if self.req_tx.send((method.into(), params, tx)).await.is_err() {
+    /// Collect requests to send them as a single JSON-RPC batch.
+    pub fn batch(&self) -> Batch {
+        Batch {
+            req_tx: self.req_tx.clone(),
+            control_tx: self.control_tx.clone(),
+            backpressure: self.backpressure,
+            request_timeout: self.request_timeout,
+            watchdog: self.watchdog,
+            monotonic_slots: self.monotonic_slots,
+            reqs: vec![],
+        }
+    }
+
+    #[throws(SolanaClientError)]
+    async fn send<T, R>(
+        &self,
+        method: &str,
+        params: &T,
+        notifier: Option<Notifier>,
+    ) -> ResponseAwaiter<R>
+    where
+        T: Serialize,
+    {
+        let watchdog = notifier.as_ref().and(self.watchdog);
+        let (req, awaiter) =
+            make_request(method, params, notifier, watchdog, self.request_timeout)?;
+        if self.req_tx.send(vec![req]).await.is_err() {
             throw!(SolanaClientError::BackgroundProcessExited);
         }
 
-        ResponseAwaiter {
-            rx,
-            _phantom: PhantomData,
+        awaiter
+    }
+}
+
+#[throws(SolanaClientError)]
+fn make_request<T, R>(
+    method: &str,
+    params: &T,
+    notifier: Option<Notifier>,
+    watchdog: Option<Watchdog>,
+    timeout: Option<Duration>,
+) -> (Request, ResponseAwaiter<R>)
+where
+    T: Serialize,
+{
+    let params = to_string(params)?;
+    let params = RawValue::from_string(params)?;
+
+    let (tx, rx) = oneshot::channel();
+
+    let req = Request {
+        method: method.into(),
+        params,
+        responder: tx,
+        notifier,
+        watchdog,
+    };
+
+    (req, ResponseAwaiter::new(rx, timeout))
+}
+
+/// Requests sent together over the websocket as a single JSON-RPC batch.
+/// Each request gets its own awaiter, resolved once `send` is done and the server answers.
+pub struct Batch {
+    req_tx: mpsc::Sender<Vec<Request>>,
+    control_tx: mpsc::UnboundedSender<Control>,
+    backpressure: Backpressure,
+    request_timeout: Option<Duration>,
+    watchdog: Option<Watchdog>,
+    monotonic_slots: bool,
+    reqs: Vec<Request>,
+}
+
+impl Batch {
+    #[throws(SolanaClientError)]
+    pub fn request<T, R>(&mut self, method: &str, params: &T) -> ResponseAwaiter<R>
+    where
+        T: Serialize,
+    {
+        let (req, awaiter) = make_request(method, params, None, None, self.request_timeout)?;
+        self.reqs.push(req);
+        awaiter
+    }
+
+    /// Like `Client::subscribe`, resolves to the subscription once the server answers.
+    #[throws(SolanaClientError)]
+    pub fn subscribe<T, R>(
+        &mut self,
+        method: &str,
+        params: &T,
+    ) -> impl Future<Output = MyResult<Subscription<R>>>
+    where
+        T: Serialize,
+    {
+        let (notifier, rx) = backpressure::channel(self.backpressure);
+        let (req, awaiter) = make_request(
+            method,
+            params,
+            Some(notifier),
+            self.watchdog,
+            self.request_timeout,
+        )?;
+        self.reqs.push(req);
+
+        let control_tx = self.control_tx.clone();
+        let monotonic_slots = self.monotonic_slots;
+        async move {
+            let id = awaiter.await?;
+            Ok(Subscription::new(id, rx, control_tx, monotonic_slots))
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.reqs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reqs.is_empty()
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn send(self) {
+        if self.req_tx.send(self.reqs).await.is_err() {
+            throw!(SolanaClientError::BackgroundProcessExited);
         }
     }
 }
 
 pub struct ResponseAwaiter<T> {
-    rx: oneshot::Receiver<Result<RpcResponse, RpcError>>,
-    _phantom: PhantomData<T>,
+    rx: oneshot::Receiver<Result<RpcResponse, SolanaClientError>>,
+    deadline: Option<Pin<Box<Sleep>>>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> ResponseAwaiter<T> {
+    pub(crate) fn new(
+        rx: oneshot::Receiver<Result<RpcResponse, SolanaClientError>>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            rx,
+            deadline: timeout.map(|timeout| Box::pin(sleep(timeout))),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Override the timeout of this request, counting from now.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Box::pin(sleep(timeout)));
+        self
+    }
 }
 
 impl<T> Future for ResponseAwaiter<T>
@@ -248,20 +718,28 @@ where
 {
     type Output = Result<T, SolanaClientError>;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = unsafe { self.get_unchecked_mut() }; // todo why is PhantomData<T> asked for T to be unpin?
-        let rx = Pin::new(&mut this.rx);
+        let this = self.get_mut();
 
-        match rx.poll(cx) {
-            Poll::Pending => Poll::Pending,
+        match Pin::new(&mut this.rx).poll(cx) {
+            Poll::Pending => {
+                let expired = match &mut this.deadline {
+                    Some(deadline) => deadline.as_mut().poll(cx).is_ready(),
+                    None => false,
+                };
+                if expired {
+                    // Lets the background process drop the pending request
+                    this.rx.close();
+                    Poll::Ready(Err(SolanaClientError::Timeout))
+                } else {
+                    Poll::Pending
+                }
+            }
             Poll::Ready(Err(_)) => Poll::Ready(Err(SolanaClientError::ResponderClosed)),
             Poll::Ready(Ok(Ok(r))) => match from_str(r.result.get()) {
                 Ok(r) => Poll::Ready(Ok(r)),
                 Err(e) => Poll::Ready(Err(SolanaClientError::Json(e))),
             },
-            Poll::Ready(Ok(Err(e))) => Poll::Ready(Err(SolanaClientError::RpcError {
-                code: e.error.code,
-                message: e.error.message,
-            })),
+            Poll::Ready(Ok(Err(e))) => Poll::Ready(Err(e)),
         }
     }
 }
@@ -0,0 +1,169 @@
+use crate::client::Client;
+use crate::errors::SolanaClientError;
+use crate::http_client::HttpClient;
+use fehler::throws;
+use futures::StreamExt;
+use log::{debug, warn};
+use solana_client::rpc_client::SerializableTransaction;
+use solana_client::rpc_config::{
+    RpcContextConfig, RpcSendTransactionConfig, RpcSignatureSubscribeConfig,
+};
+use solana_client::rpc_response::RpcSignatureResult;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+use std::time::Duration;
+use tokio::{select, time::interval};
+
+/// How a transaction sent by `Client::send_and_confirm` ended up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Reached the commitment at the slot.
+    Confirmed { slot: Slot },
+    /// Landed at the slot but failed.
+    Failed { slot: Slot, err: TransactionError },
+    /// The blockhash expired before the transaction landed.
+    Expired,
+}
+
+#[derive(Debug, Clone)]
+pub struct SendAndConfirmConfig {
+    pub commitment: CommitmentConfig,
+    pub send: RpcSendTransactionConfig,
+    /// How often the transaction is resent, which is also when the blockhash expiry is checked.
+    pub resend_every: Duration,
+    /// The last block height the blockhash is valid at, as returned by `getLatestBlockhash`.
+    /// Without it, the blockhash is checked with `isBlockhashValid`.
+    pub last_valid_block_height: Option<u64>,
+}
+
+impl Default for SendAndConfirmConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            send: RpcSendTransactionConfig::default(),
+            resend_every: Duration::from_secs(2),
+            last_valid_block_height: None,
+        }
+    }
+}
+
+impl Client {
+    /// Send the transaction over HTTP and wait for its signature over the websocket.
+    /// The signature is subscribed before sending so the notification cannot be missed,
+    /// and the transaction is resent until it lands or its blockhash expires.
+    #[throws(SolanaClientError)]
+    pub async fn send_and_confirm(
+        &self,
+        http: &HttpClient,
+        transaction: &impl SerializableTransaction,
+        config: SendAndConfirmConfig,
+    ) -> Confirmation {
+        let signature = *transaction.get_signature();
+        let sub_config = RpcSignatureSubscribeConfig {
+            commitment: Some(config.commitment),
+            enable_received_notification: None,
+        };
+        let mut subscription = self
+            .signature_subscribe(&signature, Some(sub_config))
+            .await?;
+
+        http.send_transaction(transaction, config.send).await?;
+
+        let mut resend = interval(config.resend_every);
+        resend.tick().await;
+
+        loop {
+            select! {
+                resp = subscription.next() => {
+                    let resp = match resp {
+                        Some(resp) => resp?,
+                        None => break Err(self.closed().await),
+                    };
+                    match resp.value {
+                        RpcSignatureResult::ProcessedSignature(result) => {
+                            let slot = resp.context.slot;
+                            break Ok(match result.err {
+                                Some(err) => Confirmation::Failed { slot, err },
+                                None => Confirmation::Confirmed { slot },
+                            });
+                        }
+                        RpcSignatureResult::ReceivedSignature(_) => {}
+                    }
+                }
+                // Failures of the HTTP checks are retried on the next tick, as the resends
+                _ = resend.tick() => {
+                    match expired(http, transaction, &config).await {
+                        Ok(true) => {
+                            // It may have landed right before expiring
+                            match landed(http, &signature, config.commitment).await {
+                                Ok(landed) => break Ok(landed.unwrap_or(Confirmation::Expired)),
+                                Err(e) => {
+                                    warn!(
+                                        "[Confirm] Cannot get the status of {}: {}",
+                                        signature, e
+                                    );
+                                    continue;
+                                }
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(e) => {
+                            warn!("[Confirm] Cannot check the expiry of {}: {}", signature, e);
+                        }
+                    }
+
+                    debug!("[Confirm] Resending {}", signature);
+                    let resent = http.send_transaction(transaction, config.send).await;
+                    if let Err(e) = resent {
+                        warn!("[Confirm] Cannot resend {}: {}", signature, e);
+                    }
+                }
+            }
+        }?
+    }
+}
+
+/// Whether the blockhash of the transaction can no longer land.
+#[throws(SolanaClientError)]
+async fn expired(
+    http: &HttpClient,
+    transaction: &impl SerializableTransaction,
+    config: &SendAndConfirmConfig,
+) -> bool {
+    let context = RpcContextConfig {
+        commitment: Some(config.commitment),
+        min_context_slot: None,
+    };
+
+    match config.last_valid_block_height {
+        Some(last_valid) => http.get_block_height(Some(context)).await? > last_valid,
+        None => {
+            !http
+                .is_blockhash_valid(transaction.get_recent_blockhash(), Some(context))
+                .await?
+                .value
+        }
+    }
+}
+
+/// The outcome of the signature if it reached the commitment.
+#[throws(SolanaClientError)]
+async fn landed(
+    http: &HttpClient,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Option<Confirmation> {
+    let statuses = http.get_signature_statuses(&[*signature], None).await?;
+    match statuses.value.into_iter().next().flatten() {
+        Some(status) if status.satisfies_commitment(commitment) => Some(match status.err {
+            Some(err) => Confirmation::Failed {
+                slot: status.slot,
+                err,
+            },
+            None => Confirmation::Confirmed { slot: status.slot },
+        }),
+        _ => None,
+    }
+}
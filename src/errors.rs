@@ -1,3 +1,4 @@
+use crate::rpc_message::RpcError;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, SolanaClientError>;
@@ -7,12 +8,27 @@ pub enum SolanaClientError {
     #[error("Background process exited")]
     BackgroundProcessExited,
 
+    #[error("Client is shut down")]
+    Shutdown,
+
     #[error("Responder closed")]
     ResponderClosed,
 
     #[error("Subscription receiver is dropped")]
     SubscriptionDropped,
 
+    #[error("Request timed out")]
+    Timeout,
+
+    #[error("Receiver lagged behind, {0} notifications dropped")]
+    Lagged(u64),
+
+    #[error("Websocket received nothing for {0:?}")]
+    Stale(std::time::Duration),
+
+    #[error("Subscription received nothing for {0:?}")]
+    Stalled(std::time::Duration),
+
     #[error("Websocket closed, reason: {0:?}")]
     WsClosed(Option<String>),
 
@@ -22,11 +38,18 @@ pub enum SolanaClientError {
     #[error("No host name")]
     NoHostName,
 
+    #[error("No url")]
+    NoUrl,
+
     #[error("{0}")]
     Upstream(String),
 
+    #[error("Cannot decode account: {0}")]
+    Decode(String),
+
+    // Boxed, tungstenite errors are several times larger than the others
     #[error(transparent)]
-    Websocket(#[from] tungstenite::Error),
+    Websocket(Box<tungstenite::Error>),
 
     #[error(transparent)]
     Json(#[from] serde_json::Error),
@@ -39,6 +62,38 @@ pub enum SolanaClientError {
 
     #[error(transparent)]
     Url(#[from] url::ParseError),
+
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+}
+
+impl SolanaClientError {
+    /// Whether the error means the websocket connection is lost and
+    /// the background process should reconnect.
+    pub fn is_disconnect(&self) -> bool {
+        matches!(
+            self,
+            SolanaClientError::Websocket(_)
+                | SolanaClientError::WsClosed(_)
+                | SolanaClientError::Stale(_)
+                | SolanaClientError::Stalled(_)
+        )
+    }
+}
+
+impl From<RpcError> for SolanaClientError {
+    fn from(e: RpcError) -> Self {
+        SolanaClientError::RpcError {
+            code: e.error.code,
+            message: e.error.message,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for SolanaClientError {
+    fn from(e: tungstenite::Error) -> Self {
+        SolanaClientError::Websocket(Box::new(e))
+    }
 }
 
 impl Clone for SolanaClientError {
@@ -51,7 +106,9 @@ impl Clone for SolanaClientError {
             Subscription(e) => Upstream(format!("Subscription error: {:?}", e)),
             Http(e) => Upstream(format!("Http error: {:?}", e)),
             Url(e) => Upstream(format!("Url parse error: {:?}", e)),
+            Reqwest(e) => Upstream(format!("Reqwest error: {:?}", e)),
             BackgroundProcessExited => BackgroundProcessExited,
+            Shutdown => Shutdown,
             ResponderClosed => ResponderClosed,
             WsClosed(s) => WsClosed(s.clone()),
             RpcError { code, message } => RpcError {
@@ -59,8 +116,14 @@ impl Clone for SolanaClientError {
                 message: message.clone(),
             },
             Upstream(s) => Upstream(s.clone()),
+            Decode(s) => Decode(s.clone()),
             SubscriptionDropped => SubscriptionDropped,
+            Lagged(n) => Lagged(*n),
+            Timeout => Timeout,
+            Stale(timeout) => Stale(*timeout),
+            Stalled(cadence) => Stalled(*cadence),
             NoHostName => NoHostName,
+            NoUrl => NoUrl,
         }
     }
 }
@@ -0,0 +1,346 @@
+use crate::client::ResponseAwaiter;
+use crate::errors::SolanaClientError;
+use crate::rpc_message::{RpcError, RpcRequest, RpcResponse};
+use crate::Responder;
+use fehler::{throw, throws};
+use log::{trace, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use serde_json::{from_str, json, to_string};
+use solana_account_decoder::UiAccount;
+use solana_client::rpc_client::SerializableTransaction;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcBlockConfig, RpcContextConfig, RpcProgramAccountsConfig,
+    RpcSendTransactionConfig, RpcSignatureStatusConfig, RpcSimulateTransactionConfig,
+    RpcTransactionConfig,
+};
+use solana_client::rpc_response::{
+    OptionalContext, Response, RpcBlockhash, RpcKeyedAccount, RpcSimulateTransactionResult,
+};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionStatus, UiConfirmedBlock,
+    UiTransactionEncoding,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// An async JSON-RPC client over HTTP. Cloning it is cheap.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    url: String,
+    client: reqwest::Client,
+    reqid: Arc<AtomicU64>,
+}
+
+impl HttpClient {
+    #[throws(SolanaClientError)]
+    pub(crate) fn new(
+        url: &str,
+        headers: &HashMap<String, String>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (key, value) in headers {
+            let key = HeaderName::from_bytes(key.as_bytes()).map_err(http::Error::from)?;
+            let value = HeaderValue::from_str(value).map_err(http::Error::from)?;
+            header_map.insert(key, value);
+        }
+
+        let mut builder = reqwest::Client::builder().default_headers(header_map);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        let client = builder.build()?;
+
+        HttpClient {
+            url: url.into(),
+            client,
+            reqid: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_account_info(
+        &self,
+        pubkey: &Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Response<Option<UiAccount>> {
+        self.request("getAccountInfo", &json! {[pubkey.to_string(), config]})
+            .await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Response<Vec<Option<UiAccount>>> {
+        let pubkeys: Vec<_> = pubkeys.iter().map(|pubkey| pubkey.to_string()).collect();
+        self.request("getMultipleAccounts", &json! {[pubkeys, config]})
+            .await?
+    }
+
+    /// The response carries the context only if `with_context` is set in the config.
+    #[throws(SolanaClientError)]
+    pub async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> OptionalContext<Vec<RpcKeyedAccount>> {
+        self.request(
+            "getProgramAccounts",
+            &json! {[program_id.to_string(), config]},
+        )
+        .await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_slot(&self, config: Option<RpcContextConfig>) -> Slot {
+        self.request("getSlot", &json! {[config]}).await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_block_height(&self, config: Option<RpcContextConfig>) -> u64 {
+        self.request("getBlockHeight", &json! {[config]}).await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        config: Option<RpcContextConfig>,
+    ) -> Response<bool> {
+        self.request("isBlockhashValid", &json! {[blockhash.to_string(), config]})
+            .await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_latest_blockhash(
+        &self,
+        commitment: Option<CommitmentConfig>,
+    ) -> Response<RpcBlockhash> {
+        self.request("getLatestBlockhash", &json! {[commitment]})
+            .await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+        config: Option<RpcSignatureStatusConfig>,
+    ) -> Response<Vec<Option<TransactionStatus>>> {
+        let signatures: Vec<_> = signatures.iter().map(|sig| sig.to_string()).collect();
+        self.request("getSignatureStatuses", &json! {[signatures, config]})
+            .await?
+    }
+
+    /// Send the transaction base64 encoded, unless another encoding is set in the config.
+    #[throws(SolanaClientError)]
+    pub async fn send_transaction(
+        &self,
+        transaction: &impl SerializableTransaction,
+        mut config: RpcSendTransactionConfig,
+    ) -> Signature {
+        let encoding = *config.encoding.get_or_insert(UiTransactionEncoding::Base64);
+        let transaction = encode_transaction(transaction, encoding)?;
+        let signature: String = self
+            .request("sendTransaction", &json! {[transaction, config]})
+            .await?;
+        signature
+            .parse()
+            .map_err(|e| SolanaClientError::Upstream(format!("Invalid signature: {}", e)))?
+    }
+
+    /// Simulate the transaction base64 encoded, unless another encoding is set in the config.
+    #[throws(SolanaClientError)]
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &impl SerializableTransaction,
+        mut config: RpcSimulateTransactionConfig,
+    ) -> Response<RpcSimulateTransactionResult> {
+        let encoding = *config.encoding.get_or_insert(UiTransactionEncoding::Base64);
+        let transaction = encode_transaction(transaction, encoding)?;
+        self.request("simulateTransaction", &json! {[transaction, config]})
+            .await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_block(&self, slot: Slot, config: Option<RpcBlockConfig>) -> UiConfirmedBlock {
+        self.request("getBlock", &json! {[slot, config]}).await?
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+        config: Option<RpcTransactionConfig>,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        self.request("getTransaction", &json! {[signature.to_string(), config]})
+            .await?
+    }
+
+    /// Collect requests to send them as a single JSON-RPC batch.
+    pub fn batch(&self) -> HttpBatch {
+        HttpBatch {
+            client: self.clone(),
+            reqs: vec![],
+            responders: HashMap::new(),
+        }
+    }
+
+    #[throws(SolanaClientError)]
+    pub async fn request<T, R>(&self, method: &str, params: &T) -> R
+    where
+        T: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = self.id();
+        let req = RpcRequest::new(id, method, params);
+        let body = self.post(to_string(&req)?).await?;
+
+        match from_str::<RpcResponse>(&body) {
+            Ok(resp) => from_str(resp.result.get())?,
+            Err(_) => throw!(parse_error(&body)),
+        }
+    }
+
+    fn id(&self) -> u64 {
+        self.reqid.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Post a JSON-RPC payload and return the response body.
+    #[throws(SolanaClientError)]
+    async fn post(&self, body: String) -> String {
+        trace!("[Http] Post {}", body);
+        let resp = self
+            .client
+            .post(&self.url)
+            .body(body)
+            .send()
+            .await
+            .map_err(from_reqwest)?;
+        let status = resp.status();
+        let body = resp.text().await.map_err(from_reqwest)?;
+        trace!("[Http] Received {}: {}", status, body);
+
+        if !status.is_success() && from_str::<Box<RawValue>>(&body).is_err() {
+            throw!(SolanaClientError::Upstream(format!(
+                "HTTP error {}: {}",
+                status, body
+            )));
+        }
+        body
+    }
+}
+
+/// Requests posted together as a single JSON-RPC batch.
+/// Each request gets its own awaiter, resolved once `send` is done.
+pub struct HttpBatch {
+    client: HttpClient,
+    reqs: Vec<RpcRequest>,
+    responders: HashMap<u64, Responder>,
+}
+
+impl HttpBatch {
+    #[throws(SolanaClientError)]
+    pub fn request<T, R>(&mut self, method: &str, params: &T) -> ResponseAwaiter<R>
+    where
+        T: Serialize,
+    {
+        let params = RawValue::from_string(to_string(params)?)?;
+        let id = self.client.id();
+        let (tx, rx) = oneshot::channel();
+
+        self.reqs.push(RpcRequest::new(id, method, params));
+        self.responders.insert(id, tx);
+        ResponseAwaiter::new(rx, None)
+    }
+
+    pub fn len(&self) -> usize {
+        self.reqs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reqs.is_empty()
+    }
+
+    /// Post the batch. Requests missing from the response resolve to `ResponderClosed`.
+    #[throws(SolanaClientError)]
+    pub async fn send(mut self) {
+        if self.reqs.is_empty() {
+            return;
+        }
+
+        let body = self.client.post(to_string(&self.reqs)?).await?;
+        let items = match from_str::<Vec<Box<RawValue>>>(&body) {
+            Ok(items) => items,
+            Err(_) => throw!(parse_error(&body)),
+        };
+
+        for item in items {
+            let (id, resp) = match from_str::<RpcResponse>(item.get()) {
+                Ok(resp) => (resp.id, Ok(resp)),
+                Err(_) => match from_str::<RpcError>(item.get()) {
+                    Ok(error) => (error.id, Err(error.into())),
+                    Err(e) => {
+                        warn!("Cannot deserialize batch item {}, error: {:?}", item, e);
+                        continue;
+                    }
+                },
+            };
+
+            match self.responders.remove(&id) {
+                Some(responder) => {
+                    if responder.send(resp).is_err() {
+                        trace!("Responder for req: {} droppped", id);
+                    }
+                }
+                None => warn!("Responder for req: {} not found", id),
+            }
+        }
+    }
+}
+
+fn from_reqwest(e: reqwest::Error) -> SolanaClientError {
+    if e.is_timeout() {
+        SolanaClientError::Timeout
+    } else {
+        SolanaClientError::Reqwest(e)
+    }
+}
+
+fn parse_error(body: &str) -> SolanaClientError {
+    match from_str::<RpcError>(body) {
+        Ok(e) => e.into(),
+        Err(e) => SolanaClientError::Json(e),
+    }
+}
+
+#[throws(SolanaClientError)]
+fn encode_transaction(
+    transaction: &impl SerializableTransaction,
+    encoding: UiTransactionEncoding,
+) -> String {
+    let bytes = bincode::serialize(transaction)
+        .map_err(|e| SolanaClientError::Upstream(format!("Bincode error: {}", e)))?;
+
+    match encoding {
+        UiTransactionEncoding::Base58 => bs58::encode(bytes).into_string(),
+        UiTransactionEncoding::Base64 => base64::encode(bytes),
+        _ => throw!(SolanaClientError::Upstream(format!(
+            "Unsupported transaction encoding {:?}",
+            encoding
+        ))),
+    }
+}
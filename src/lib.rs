@@ -1,18 +1,48 @@
+pub mod account;
+pub mod account_cache;
+pub mod account_view;
 pub mod background;
+pub mod backpressure;
+pub mod blockhash;
 pub mod client;
+pub mod confirm;
 pub mod errors;
+pub mod http_client;
+pub mod reconnect;
+pub mod redundant;
 pub mod rpc_message;
+pub mod subscription;
+pub mod typed;
+pub mod watchdog;
 
 pub mod prelude {
+    pub use crate::account::{Account, AccountData, KeyedAccount, ParsedAccountData};
+    pub use crate::account_cache::{AccountCache, CachedAccount};
+    pub use crate::account_view::{AccountView, DualAccountSubscription};
     pub use crate::background::BackgroundProcess;
-    pub use crate::client::{Client, ClientBuilder};
+    pub use crate::backpressure::Backpressure;
+    pub use crate::blockhash::{BlockhashTracker, BlockhashTrackerConfig, LatestBlockhash};
+    pub use crate::client::{Client, ClientBuilder, Endpoint};
+    pub use crate::confirm::{Confirmation, SendAndConfirmConfig};
     pub use crate::errors::SolanaClientError;
+    pub use crate::http_client::HttpClient;
+    pub use crate::reconnect::ReconnectPolicy;
+    pub use crate::redundant::{RedundantClient, RedundantSubscription, Update};
+    pub use crate::subscription::Subscription;
+    pub use crate::typed::{anchor_discriminator, decode_account, AccountDecode};
+    #[cfg(feature = "borsh")]
+    pub use crate::typed::{decode_borsh, Borsh};
+    #[cfg(feature = "bytemuck")]
+    pub use crate::typed::{decode_pod, Pod};
+    pub use crate::watchdog::{OnStall, Watchdog};
+    pub use tungstenite::protocol::frame::coding::CloseCode;
 }
 
-use crate::rpc_message::{RpcError, RpcResponse};
+use crate::errors::SolanaClientError;
+use crate::rpc_message::RpcResponse;
 use tokio::net::TcpStream;
 use tokio::sync::oneshot;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
 type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
-type Responder = oneshot::Sender<Result<RpcResponse, RpcError>>;
+type Responder = oneshot::Sender<Result<RpcResponse, SolanaClientError>>;
@@ -0,0 +1,139 @@
+use crate::errors::SolanaClientError;
+use rand::Rng;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+type GiveUpCallback = Arc<dyn Fn(&SolanaClientError) + Send + Sync>;
+
+/// Controls how the background process reconnects after the websocket drops.
+///
+/// The delay before the n-th attempt is `initial_backoff * multiplier^n`, capped at
+/// `max_backoff`, and then reduced by a random fraction of up to `jitter`.
+#[derive(Clone)]
+pub struct ReconnectPolicy {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    max_attempts: Option<u32>,
+    max_elapsed: Option<Duration>,
+    on_give_up: Option<GiveUpCallback>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.,
+            jitter: 0.2,
+            max_attempts: None,
+            max_elapsed: None,
+            on_give_up: None,
+        }
+    }
+}
+
+impl fmt::Debug for ReconnectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectPolicy")
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("max_attempts", &self.max_attempts)
+            .field("max_elapsed", &self.max_elapsed)
+            .field("on_give_up", &self.on_give_up.is_some())
+            .finish()
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Never reconnect, the background process exits on the first disconnection.
+    pub fn never() -> Self {
+        let mut policy = Self::new();
+        policy.max_attempts(0);
+        policy
+    }
+
+    pub fn initial_backoff(&mut self, initial_backoff: Duration) -> &mut Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn max_backoff(&mut self, max_backoff: Duration) -> &mut Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn multiplier(&mut self, multiplier: f64) -> &mut Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Fraction in `[0, 1]` of the delay that is randomly taken off.
+    pub fn jitter(&mut self, jitter: f64) -> &mut Self {
+        self.jitter = jitter.clamp(0., 1.);
+        self
+    }
+
+    pub fn max_attempts(&mut self, max_attempts: u32) -> &mut Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Give up once this much time has passed since the disconnection.
+    pub fn max_elapsed(&mut self, max_elapsed: Duration) -> &mut Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Called with the last error when the background process gives up reconnecting.
+    pub fn on_give_up<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&SolanaClientError) + Send + Sync + 'static,
+    {
+        self.on_give_up = Some(Arc::new(f));
+        self
+    }
+
+    /// The delay before the `attempt`-th (starting from 0) reconnect attempt, without jitter.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.min(i32::MAX as u32) as i32);
+        let delay = self.initial_backoff.as_secs_f64() * factor;
+        if delay.is_finite() && delay < self.max_backoff.as_secs_f64() {
+            Duration::from_secs_f64(delay)
+        } else {
+            self.max_backoff
+        }
+    }
+
+    /// The delay before the next attempt, or `None` if the policy gives up.
+    pub(crate) fn next_delay(&self, attempt: u32, elapsed: Duration) -> Option<Duration> {
+        if matches!(self.max_attempts, Some(max) if attempt >= max) {
+            return None;
+        }
+
+        let mut delay = self.backoff(attempt);
+        if self.jitter > 0. {
+            delay = delay.mul_f64(1. - self.jitter * rand::thread_rng().gen::<f64>());
+        }
+
+        if matches!(self.max_elapsed, Some(max) if elapsed + delay > max) {
+            return None;
+        }
+
+        Some(delay)
+    }
+
+    pub(crate) fn give_up(&self, error: &SolanaClientError) {
+        if let Some(f) = &self.on_give_up {
+            f(error)
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use crate::client::{Client, ClientBuilder};
+use crate::errors::{Result as MyResult, SolanaClientError};
+use crate::subscription::Subscription;
+use fehler::{throw, throws};
+use futures::{
+    future::{join_all, BoxFuture, Future},
+    stream::FuturesUnordered,
+    task::{Context, Poll},
+    Stream, StreamExt,
+};
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{to_string, to_value};
+use solana_account_decoder::UiAccount;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use solana_client::rpc_response::{Response, RpcLogsResponse, SlotInfo};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use tokio::sync::watch;
+use tokio::time::{sleep, Instant};
+use tokio::{select, spawn};
+
+// Number of recent update keys remembered to drop the copies from slower endpoints.
+const DEDUP_WINDOW: usize = 4096;
+
+/// Clients connected to several endpoints at once, subscribing to the same feeds
+/// on all of them. Built with `ClientBuilder::build_redundant`.
+#[derive(Clone)]
+pub struct RedundantClient {
+    // By endpoint index. `None` while an endpoint down at startup is being connected,
+    // the last error once its reconnect policy gives up.
+    clients: Vec<watch::Receiver<Option<MyResult<Client>>>>,
+}
+
+/// An update together with the index of the endpoint that delivered it first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update<T> {
+    pub endpoint: usize,
+    pub value: T,
+}
+
+type Joining<T> = BoxFuture<'static, (usize, MyResult<Subscription<T>>)>;
+
+impl RedundantClient {
+    /// Connect every endpoint at once. The endpoints down are connected in the
+    /// background following the reconnect policy, fails only if none is up.
+    #[throws(SolanaClientError)]
+    pub(crate) async fn connect(mut builders: Vec<ClientBuilder>) -> Self {
+        let results = join_all(builders.iter_mut().map(|builder| builder.build())).await;
+        if results.iter().all(Result::is_err) {
+            let error = results.into_iter().filter_map(Result::err).last();
+            throw!(error.unwrap_or(SolanaClientError::NoUrl));
+        }
+
+        let mut clients = vec![];
+        for (endpoint, (builder, result)) in builders.into_iter().zip(results).enumerate() {
+            match result {
+                Ok(client) => clients.push(watch::channel(Some(Ok(client))).1),
+                Err(e) => {
+                    warn!(
+                        "[Redundant] Cannot connect to endpoint {}, retrying: {}",
+                        endpoint, e
+                    );
+                    let (tx, rx) = watch::channel(None);
+                    spawn(connect_later(endpoint, builder, tx, e));
+                    clients.push(rx);
+                }
+            }
+        }
+        Self { clients }
+    }
+
+    /// The clients, in the order of the endpoints. `None` for the endpoints not
+    /// connected since startup.
+    pub fn clients(&self) -> Vec<Option<Client>> {
+        self.clients
+            .iter()
+            .map(|client| client.borrow().clone().and_then(Result::ok))
+            .collect()
+    }
+
+    /// Subscribe on every endpoint. Waits for the connected ones, the others join
+    /// the subscription once connected.
+    #[throws(SolanaClientError)]
+    async fn subscribe_all<T, F, Fut>(
+        &self,
+        subscribe: F,
+        key: fn(&T) -> u64,
+    ) -> RedundantSubscription<T>
+    where
+        T: 'static,
+        F: Fn(Client) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = MyResult<Subscription<T>>> + Send,
+    {
+        let mut connected = vec![];
+        let joining = FuturesUnordered::new();
+        for (endpoint, client) in self.clients.iter().enumerate() {
+            let sub = subscribe_when_connected(endpoint, client.clone(), subscribe.clone());
+            match client.borrow().is_some() {
+                true => connected.push(sub),
+                false => joining.push(sub),
+            }
+        }
+
+        let mut sub = RedundantSubscription::new(self.clients.len(), joining, key);
+        for (endpoint, result) in join_all(connected).await {
+            sub.join(endpoint, result);
+        }
+        // Nothing connected is left, wait for the first endpoint to come back
+        while sub.feeds.is_empty() {
+            match sub.joining.next().await {
+                Some((endpoint, result)) => sub.join(endpoint, result),
+                None => throw!(sub.last_error.take().unwrap_or(SolanaClientError::NoUrl)),
+            }
+        }
+        sub
+    }
+
+    /// Updates are deduplicated by the slot and the account content.
+    #[throws(SolanaClientError)]
+    pub async fn account_subscribe(
+        &self,
+        pubkey: &Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> RedundantSubscription<Response<UiAccount>> {
+        let pubkey = *pubkey;
+        let subscribe = move |client: Client| {
+            let config = config.clone();
+            async move { client.account_subscribe(&pubkey, config).await }
+        };
+        self.subscribe_all(subscribe, |resp: &Response<UiAccount>| {
+            key(&(
+                resp.context.slot,
+                to_string(&resp.value).unwrap_or_default(),
+            ))
+        })
+        .await?
+    }
+
+    /// Updates are deduplicated by the slot.
+    #[throws(SolanaClientError)]
+    pub async fn slot_subscribe(&self) -> RedundantSubscription<SlotInfo> {
+        let subscribe = |client: Client| async move { client.slot_subscribe().await };
+        self.subscribe_all(subscribe, |info: &SlotInfo| info.slot)
+            .await?
+    }
+
+    /// Updates are deduplicated by the transaction signature.
+    #[throws(SolanaClientError)]
+    pub async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> RedundantSubscription<Response<RpcLogsResponse>> {
+        let subscribe = move |client: Client| {
+            let (filter, config) = (filter.clone(), config.clone());
+            async move { client.logs_subscribe(filter, config).await }
+        };
+        self.subscribe_all(subscribe, |resp: &Response<RpcLogsResponse>| {
+            key(&resp.value.signature)
+        })
+        .await?
+    }
+
+    /// Subscribe with an arbitrary method, deduplicating the updates by `key`.
+    #[throws(SolanaClientError)]
+    pub async fn subscribe<T, R>(
+        &self,
+        method: &str,
+        params: &T,
+        key: fn(&R) -> u64,
+    ) -> RedundantSubscription<R>
+    where
+        T: Serialize,
+        R: 'static,
+    {
+        let method = method.to_string();
+        let params = to_value(params)?;
+        let subscribe = move |client: Client| {
+            let (method, params) = (method.clone(), params.clone());
+            async move { client.subscribe(&method, &params).await }
+        };
+        self.subscribe_all(subscribe, key).await?
+    }
+}
+
+/// Subscribe on the endpoint once its client is connected.
+fn subscribe_when_connected<T, F, Fut>(
+    endpoint: usize,
+    mut client: watch::Receiver<Option<MyResult<Client>>>,
+    subscribe: F,
+) -> Joining<T>
+where
+    T: 'static,
+    F: Fn(Client) -> Fut + Send + 'static,
+    Fut: Future<Output = MyResult<Subscription<T>>> + Send,
+{
+    Box::pin(async move {
+        let connected = loop {
+            let connected = client.borrow().clone();
+            if let Some(connected) = connected {
+                break connected;
+            }
+            if client.changed().await.is_err() {
+                break Err(SolanaClientError::NoUrl);
+            }
+        };
+        match connected {
+            Ok(client) => (endpoint, subscribe(client).await),
+            Err(e) => (endpoint, Err(e)),
+        }
+    })
+}
+
+/// Connect an endpoint down at startup following its reconnect policy, until the
+/// policy gives up or the redundant client and its subscriptions are dropped.
+async fn connect_later(
+    endpoint: usize,
+    mut builder: ClientBuilder,
+    tx: watch::Sender<Option<MyResult<Client>>>,
+    mut error: SolanaClientError,
+) {
+    let since = Instant::now();
+    let mut attempt = 0;
+
+    loop {
+        let delay = match builder
+            .reconnect_policy
+            .next_delay(attempt, since.elapsed())
+        {
+            Some(delay) => delay,
+            None => {
+                warn!("[Redundant] Giving up connecting to endpoint {}", endpoint);
+                builder.reconnect_policy.give_up(&error);
+                tx.send_replace(Some(Err(error)));
+                return;
+            }
+        };
+        select! {
+            _ = sleep(delay) => {}
+            _ = tx.closed() => return,
+        }
+        attempt += 1;
+
+        match builder.build().await {
+            Ok(client) => {
+                info!(
+                    "[Redundant] Connected to endpoint {} after {} attempts",
+                    endpoint, attempt
+                );
+                tx.send_replace(Some(Ok(client)));
+                return;
+            }
+            Err(e) => {
+                warn!(
+                    "[Redundant] Connect attempt {} to endpoint {} failed: {}",
+                    attempt, endpoint, e
+                );
+                error = e;
+            }
+        }
+    }
+}
+
+/// A stream of the first copy of each update across the endpoints.
+///
+/// Failures of a single endpoint are only logged, the stream yields an error
+/// once every endpoint has failed.
+pub struct RedundantSubscription<T> {
+    feeds: Vec<Feed<T>>,
+    // Subscriptions of the endpoints not connected yet.
+    joining: FuturesUnordered<Joining<T>>,
+    key: fn(&T) -> u64,
+    seen: HashSet<u64>,
+    order: VecDeque<u64>,
+    wins: Vec<u64>,
+    last_error: Option<SolanaClientError>,
+}
+
+/// The subscription of one endpoint, with its next update held until it is
+/// compared to the other endpoints.
+struct Feed<T> {
+    endpoint: usize,
+    sub: Subscription<T>,
+    // When the update was received, its key, the update.
+    head: Option<(Instant, u64, T)>,
+}
+
+impl<T> RedundantSubscription<T> {
+    fn new(endpoints: usize, joining: FuturesUnordered<Joining<T>>, key: fn(&T) -> u64) -> Self {
+        Self {
+            feeds: vec![],
+            joining,
+            key,
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            wins: vec![0; endpoints],
+            last_error: None,
+        }
+    }
+
+    /// Add the subscription of an endpoint, or remember why it failed.
+    fn join(&mut self, endpoint: usize, result: MyResult<Subscription<T>>) {
+        match result {
+            Ok(sub) => self.feeds.push(Feed {
+                endpoint,
+                sub,
+                head: None,
+            }),
+            Err(e) => {
+                warn!(
+                    "[Redundant] Cannot subscribe on endpoint {}: {}",
+                    endpoint, e
+                );
+                self.last_error = Some(e);
+            }
+        }
+    }
+
+    /// Number of updates each endpoint delivered first, by endpoint index.
+    pub fn wins(&self) -> &[u64] {
+        &self.wins
+    }
+
+    /// Remember the key, returns false if it was seen already.
+    fn first_seen(&mut self, key: u64) -> bool {
+        if !self.seen.insert(key) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > DEDUP_WINDOW {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// The held updates are never pinned
+impl<T> Unpin for RedundantSubscription<T> {}
+
+impl<T> Stream for RedundantSubscription<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = MyResult<Update<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        while let Poll::Ready(Some((endpoint, result))) = this.joining.poll_next_unpin(cx) {
+            debug!("[Redundant] Endpoint {} joining the subscription", endpoint);
+            this.join(endpoint, result);
+        }
+
+        // Fill the head of every endpoint with its next update not delivered yet
+        let mut i = 0;
+        while i < this.feeds.len() {
+            let feed = &mut this.feeds[i];
+            if matches!(&feed.head, Some((_, key, _)) if this.seen.contains(key)) {
+                feed.head = None;
+            }
+            if feed.head.is_some() {
+                i += 1;
+                continue;
+            }
+
+            match feed.sub.poll_recv(cx) {
+                Poll::Pending => i += 1,
+                Poll::Ready(Some((received, Ok(value)))) => {
+                    let key = (this.key)(&value);
+                    if !this.seen.contains(&key) {
+                        feed.head = Some((received, key, value));
+                    }
+                }
+                Poll::Ready(Some((_, Err(e)))) => {
+                    warn!("[Redundant] Endpoint {} failed: {}", feed.endpoint, e);
+                    this.last_error = Some(e);
+                }
+                Poll::Ready(None) => {
+                    this.feeds.remove(i);
+                }
+            }
+        }
+
+        // The update received first wins, whichever queue is polled first
+        let first = this
+            .feeds
+            .iter_mut()
+            .filter(|feed| feed.head.is_some())
+            .min_by_key(|feed| feed.head.as_ref().map(|(received, _, _)| *received));
+        if let Some(feed) = first {
+            let endpoint = feed.endpoint;
+            let (_, key, value) = feed.head.take().unwrap();
+            this.first_seen(key);
+            this.wins[endpoint] += 1;
+            return Poll::Ready(Some(Ok(Update { endpoint, value })));
+        }
+
+        if this.feeds.is_empty() && this.joining.is_empty() {
+            return Poll::Ready(this.last_error.take().map(Err));
+        }
+        Poll::Pending
+    }
+}
+
+fn key(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
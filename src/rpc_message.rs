@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
+use tokio::time::Instant;
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct RpcNotificationParams<T = Box<RawValue>> {
@@ -12,6 +13,9 @@ pub struct RpcNotification<T = Box<RawValue>> {
     pub jsonrpc: String,
     pub method: String,
     pub params: RpcNotificationParams<T>,
+    /// When the notification was received, it is parsed as soon as it arrives.
+    #[serde(skip, default = "Instant::now")]
+    pub received: Instant,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -0,0 +1,151 @@
+use crate::background::Control;
+use crate::backpressure::Receiver;
+use crate::errors::{Result as MyResult, SolanaClientError};
+use futures::{
+    task::{Context, Poll},
+    Stream,
+};
+use log::{debug, trace};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{from_str, value::RawValue};
+use solana_sdk::clock::Slot;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// A stream of the notifications of a single subscription.
+///
+/// Dropping the subscription unsubscribes it on the server.
+pub struct Subscription<T> {
+    id: u64,
+    rx: Receiver,
+    control_tx: mpsc::UnboundedSender<Control>,
+    // The last context slot delivered, by pubkey, when monotonic slots are enforced.
+    last_slots: Option<HashMap<Option<String>, Slot>>,
+    rejected: u64,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+/// The parts of a `Response` notification telling its slot and its account.
+#[derive(Deserialize)]
+struct Header {
+    context: HeaderContext,
+    #[serde(default)]
+    value: HeaderValue,
+}
+
+#[derive(Deserialize)]
+struct HeaderContext {
+    slot: Slot,
+}
+
+#[derive(Deserialize, Default)]
+struct HeaderValue {
+    pubkey: Option<String>,
+}
+
+impl<T> Subscription<T> {
+    pub(crate) fn new(
+        id: u64,
+        rx: Receiver,
+        control_tx: mpsc::UnboundedSender<Control>,
+        monotonic_slots: bool,
+    ) -> Self {
+        Self {
+            id,
+            rx,
+            control_tx,
+            last_slots: monotonic_slots.then(HashMap::new),
+            rejected: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The subscription id, stable across reconnects.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Number of notifications dropped by the backpressure policy.
+    pub fn dropped(&self) -> u64 {
+        self.rx.dropped()
+    }
+
+    /// Number of notifications dropped for going back in slots, see `Client::with_monotonic_slots`.
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Whether the notification goes back in slots, remembering its slot otherwise.
+    fn regressed(&mut self, notif: &RawValue) -> bool {
+        let last_slots = match &mut self.last_slots {
+            Some(last_slots) => last_slots,
+            None => return false,
+        };
+        let header: Header = match from_str(notif.get()) {
+            Ok(header) => header,
+            Err(_) => return false,
+        };
+
+        let slot = header.context.slot;
+        let last = last_slots.entry(header.value.pubkey).or_insert(slot);
+        if slot < *last {
+            debug!(
+                "[Subscription] Rejecting slot {} after {} on subscription {}",
+                slot, last, self.id
+            );
+            self.rejected += 1;
+            return true;
+        }
+        *last = slot;
+        false
+    }
+}
+
+impl<T: DeserializeOwned> Subscription<T> {
+    /// The next notification with the time the background process received it.
+    pub(crate) fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<(Instant, MyResult<T>)>> {
+        loop {
+            match self.rx.poll_recv(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Ready(Some((received, Err(e)))) => {
+                    return Poll::Ready(Some((received, Err(e))))
+                }
+                Poll::Ready(Some((received, Ok(notif)))) => {
+                    trace!("[Subscription] Recv payload: {}", notif);
+                    if self.regressed(&notif) {
+                        continue;
+                    }
+                    let item = from_str(notif.get()).map_err(SolanaClientError::Json);
+                    return Poll::Ready(Some((received, item)));
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let _ = self.control_tx.send(Control::Unsubscribe(self.id));
+    }
+}
+
+impl<T> Stream for Subscription<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = MyResult<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_recv(cx)
+            .map(|item| item.map(|(_, item)| item))
+    }
+}
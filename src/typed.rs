@@ -0,0 +1,155 @@
+use crate::account::{Account, KeyedAccount};
+use crate::client::Client;
+use crate::errors::{Result as MyResult, SolanaClientError};
+use fehler::{throw, throws};
+use futures::{Stream, StreamExt};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+
+/// A type decoded from the data of an account, for the typed subscriptions.
+pub trait AccountDecode: Sized {
+    /// The 8 bytes expected at the start of the data, checked and stripped before
+    /// `decode`. Anchor accounts use `anchor_discriminator`.
+    fn discriminator() -> Option<[u8; 8]> {
+        None
+    }
+
+    fn decode(data: &[u8]) -> MyResult<Self>;
+}
+
+/// The discriminator Anchor prefixes the accounts of type `name` with.
+pub fn anchor_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash(format!("account:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Check the discriminator of `T` if any, then decode the rest.
+#[throws(SolanaClientError)]
+pub fn decode_account<T: AccountDecode>(data: &[u8]) -> T {
+    match T::discriminator() {
+        Some(discriminator) => match data.strip_prefix(&discriminator[..]) {
+            Some(data) => T::decode(data)?,
+            None => throw!(SolanaClientError::Decode(format!(
+                "Discriminator mismatch, expected {:?}, got {:?}",
+                discriminator,
+                &data[..data.len().min(8)]
+            ))),
+        },
+        None => T::decode(data)?,
+    }
+}
+
+/// Decode with Borsh, ignoring the trailing bytes of the account.
+#[cfg(feature = "borsh")]
+#[throws(SolanaClientError)]
+pub fn decode_borsh<T: borsh::BorshDeserialize>(mut data: &[u8]) -> T {
+    T::deserialize(&mut data).map_err(|e| SolanaClientError::Decode(e.to_string()))?
+}
+
+/// A Borsh type without discriminator. Implement `AccountDecode` with
+/// `decode_borsh` to check one.
+#[cfg(feature = "borsh")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Borsh<T>(pub T);
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshDeserialize> AccountDecode for Borsh<T> {
+    fn decode(data: &[u8]) -> MyResult<Self> {
+        decode_borsh(data).map(Borsh)
+    }
+}
+
+/// Read a plain old data type, as Anchor zero-copy accounts are, ignoring the trailing bytes.
+#[cfg(feature = "bytemuck")]
+#[throws(SolanaClientError)]
+pub fn decode_pod<T: bytemuck::Pod>(data: &[u8]) -> T {
+    let size = std::mem::size_of::<T>();
+    match data.get(..size) {
+        Some(data) => bytemuck::pod_read_unaligned(data),
+        None => throw!(SolanaClientError::Decode(format!(
+            "Expected at least {} bytes, got {}",
+            size,
+            data.len()
+        ))),
+    }
+}
+
+/// A plain old data type without discriminator. Implement `AccountDecode` with
+/// `decode_pod` to check one.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pod<T>(pub T);
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> AccountDecode for Pod<T> {
+    fn decode(data: &[u8]) -> MyResult<Self> {
+        decode_pod(data).map(Pod)
+    }
+}
+
+impl Client {
+    /// Like `account_subscribe`, with the data decoded as `T`. The encoding defaults
+    /// to base64, `jsonParsed` cannot be decoded. A failure to decode is yielded
+    /// as an error without ending the stream.
+    #[throws(SolanaClientError)]
+    pub async fn account_subscribe_typed<T: AccountDecode>(
+        &self,
+        pubkey: &Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> impl Stream<Item = MyResult<(Slot, Pubkey, T)>> {
+        let mut config = config.unwrap_or_default();
+        config.encoding.get_or_insert(UiAccountEncoding::Base64);
+
+        let pubkey = *pubkey;
+        self.account_subscribe(&pubkey, Some(config))
+            .await?
+            .map(move |resp| {
+                let resp: Response<UiAccount> = resp?;
+                let account = Account::decode(&resp.value)?;
+                typed(resp.context.slot, pubkey, &account)
+            })
+    }
+
+    /// Like `program_subscribe`, with the data decoded as `T`. The encoding defaults
+    /// to base64, `jsonParsed` cannot be decoded. A failure to decode is yielded
+    /// as an error without ending the stream.
+    #[throws(SolanaClientError)]
+    pub async fn program_subscribe_typed<T: AccountDecode>(
+        &self,
+        program_id: &Pubkey,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> impl Stream<Item = MyResult<(Slot, Pubkey, T)>> {
+        let mut config = config.unwrap_or_default();
+        config
+            .account_config
+            .encoding
+            .get_or_insert(UiAccountEncoding::Base64);
+
+        self.program_subscribe(program_id, Some(config))
+            .await?
+            .map(|resp| {
+                let resp: Response<RpcKeyedAccount> = resp?;
+                let keyed = KeyedAccount::decode(&resp.value)?;
+                typed(resp.context.slot, keyed.pubkey, &keyed.account)
+            })
+    }
+}
+
+#[throws(SolanaClientError)]
+fn typed<T: AccountDecode>(slot: Slot, pubkey: Pubkey, account: &Account) -> (Slot, Pubkey, T) {
+    let data = account.bytes().ok_or_else(|| {
+        SolanaClientError::Decode(format!("{} at slot {} is jsonParsed", pubkey, slot))
+    })?;
+    let value = decode_account(data).map_err(|e| match e {
+        SolanaClientError::Decode(e) => {
+            SolanaClientError::Decode(format!("{} at slot {}: {}", pubkey, slot, e))
+        }
+        e => e,
+    })?;
+    (slot, pubkey, value)
+}
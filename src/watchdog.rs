@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+/// What to do when a subscription stays silent longer than its cadence.
+/// The subscription always receives a `Stalled` error first, which does not end the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnStall {
+    /// Only notify the subscription.
+    #[default]
+    Notify,
+    /// Unsubscribe and subscribe again on the same connection.
+    Resubscribe,
+    /// Drop the connection and reconnect following the reconnect policy.
+    Reconnect,
+}
+
+/// The cadence a subscription is expected to receive notifications at,
+/// e.g. slot updates at least every 2 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    pub(crate) cadence: Duration,
+    pub(crate) on_stall: OnStall,
+}
+
+impl Watchdog {
+    pub fn new(cadence: Duration, on_stall: OnStall) -> Self {
+        Self { cadence, on_stall }
+    }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::*;
+use serde_json::json;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client_async::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+async fn next_change(changes: &mut broadcast::Receiver<CachedAccount>) -> CachedAccount {
+    timeout(Duration::from_secs(5), changes.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn accounts() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let url = serve_http(move |req| {
+        assert_eq!(req["method"], "getMultipleAccounts");
+        assert_eq!(req["params"][0], json!([a.to_string(), b.to_string()]));
+        rpc_result(
+            &req,
+            json!({"context": {"slot": 100}, "value": [ui_account_json(1), null]}),
+        )
+    })
+    .await;
+    let (ws_url, tx) = serve_subscriptions("accountSubscribe", &[10, 11]).await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let cache = AccountCache::accounts(&client, &http, &[a, b], RpcAccountInfoConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(cache.get(&a).unwrap().slot, 100);
+    assert_eq!(cache.get(&a).unwrap().account.unwrap().lamports, 1);
+    assert_eq!(cache.get(&b).unwrap().account, None);
+    assert_eq!(cache.get(&Pubkey::new_unique()), None);
+    assert_eq!(cache.all().len(), 2);
+
+    let mut changes = cache.changes();
+    // Older than the snapshot
+    tx.send((10, account_json(99, 5))).unwrap();
+    tx.send((10, account_json(101, 2))).unwrap();
+    tx.send((11, account_json(102, 3))).unwrap();
+
+    // The subscriptions are not ordered between each other
+    let mut received = vec![];
+    for _ in 0..2 {
+        let change = next_change(&mut changes).await;
+        received.push((change.pubkey, change.slot));
+    }
+    received.sort_by_key(|(_, slot)| *slot);
+    assert_eq!(received, vec![(a, 101), (b, 102)]);
+
+    assert_eq!(cache.get(&a).unwrap().account.unwrap().lamports, 2);
+    assert_eq!(cache.get(&b).unwrap().account.unwrap().lamports, 3);
+
+    // Closed, missing like in the snapshot
+    tx.send((10, account_json(103, 0))).unwrap();
+    let change = next_change(&mut changes).await;
+    assert_eq!((change.pubkey, change.slot, change.account), (a, 103, None));
+    assert_eq!(cache.get(&a).unwrap().account, None);
+}
+
+#[tokio::test]
+async fn program() {
+    let (program_id, a, b) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let url = serve_http(move |req| {
+        assert_eq!(req["method"], "getProgramAccounts");
+        assert_eq!(req["params"][0], program_id.to_string());
+        assert_eq!(req["params"][1]["withContext"], true);
+        let value = json!([{"pubkey": a.to_string(), "account": ui_account_json(1)}]);
+        rpc_result(&req, json!({"context": {"slot": 100}, "value": value}))
+    })
+    .await;
+    let (ws_url, tx) = serve_subscriptions("programSubscribe", &[10]).await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let cache = AccountCache::program(
+        &client,
+        &http,
+        &program_id,
+        RpcProgramAccountsConfig::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(cache.get(&a).unwrap().slot, 100);
+    assert_eq!(cache.get(&b), None);
+
+    let mut changes = cache.changes();
+    tx.send((10, keyed_account_json(&a, 90, 5))).unwrap();
+    // Not in the snapshot, but older than it
+    tx.send((10, keyed_account_json(&b, 99, 1))).unwrap();
+    tx.send((10, keyed_account_json(&b, 101, 2))).unwrap();
+    tx.send((10, keyed_account_json(&b, 100, 4))).unwrap();
+    tx.send((10, keyed_account_json(&a, 102, 3))).unwrap();
+
+    let change = next_change(&mut changes).await;
+    assert_eq!((change.pubkey, change.slot), (b, 101));
+    let change = next_change(&mut changes).await;
+    assert_eq!((change.pubkey, change.slot), (a, 102));
+
+    assert_eq!(cache.get(&a).unwrap().account.unwrap().lamports, 3);
+    assert_eq!(cache.get(&b).unwrap().account.unwrap().lamports, 2);
+}
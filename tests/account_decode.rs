@@ -0,0 +1,173 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::{from_value, json, Value};
+use solana_account_decoder::parse_nonce::UiNonceState;
+use solana_account_decoder::parse_token::{TokenAccountType, UiAccountState};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client_async::prelude::*;
+use solana_sdk::account::Account as RawAccount;
+use solana_sdk::pubkey::Pubkey;
+
+fn raw_account() -> RawAccount {
+    RawAccount {
+        lamports: 42,
+        data: (0..100).collect(),
+        owner: Pubkey::new_unique(),
+        executable: false,
+        rent_epoch: 7,
+    }
+}
+
+fn parsed(program: &str, parsed: Value) -> UiAccount {
+    from_value(json!({
+        "lamports": 1,
+        "data": {"program": program, "parsed": parsed, "space": 165},
+        "owner": Pubkey::new_unique().to_string(),
+        "executable": false,
+        "rentEpoch": 0,
+    }))
+    .unwrap()
+}
+
+fn token_account(mint: &Pubkey) -> Value {
+    json!({
+        "type": "account",
+        "info": {
+            "mint": mint.to_string(),
+            "owner": Pubkey::new_unique().to_string(),
+            "tokenAmount": {
+                "uiAmount": 1.5,
+                "decimals": 6,
+                "amount": "1500000",
+                "uiAmountString": "1.5",
+            },
+            "state": "initialized",
+            "isNative": false,
+        },
+    })
+}
+
+#[test]
+fn binary_encodings() {
+    let raw = raw_account();
+    for encoding in [
+        UiAccountEncoding::Base58,
+        UiAccountEncoding::Base64,
+        UiAccountEncoding::Base64Zstd,
+    ] {
+        let ui = UiAccount::encode(&Pubkey::new_unique(), &raw, encoding, None, None);
+        let account = Account::decode(&ui).unwrap();
+        assert_eq!(account.bytes(), Some(&raw.data[..]), "{:?}", encoding);
+        assert_eq!(account.lamports, 42);
+        assert_eq!(account.owner, raw.owner);
+        assert_eq!(account.rent_epoch, 7);
+    }
+}
+
+#[test]
+fn invalid_binary() {
+    let ui: UiAccount = from_value(json!({
+        "lamports": 1,
+        "data": ["not base64!", "base64"],
+        "owner": Pubkey::new_unique().to_string(),
+        "executable": false,
+        "rentEpoch": 0,
+    }))
+    .unwrap();
+    assert!(matches!(
+        Account::decode(&ui),
+        Err(SolanaClientError::Decode(_))
+    ));
+}
+
+#[test]
+fn json_parsed() {
+    let mint = Pubkey::new_unique();
+    let account = Account::decode(&parsed("spl-token", token_account(&mint))).unwrap();
+    match account.parsed() {
+        Some(ParsedAccountData::Token(TokenAccountType::Account(token))) => {
+            assert_eq!(token.mint, mint.to_string());
+            assert_eq!(token.token_amount.amount, "1500000");
+            assert_eq!(token.state, UiAccountState::Initialized);
+        }
+        data => panic!("Unexpected data {:?}", data),
+    }
+
+    let nonce = json!({
+        "type": "initialized",
+        "info": {
+            "authority": Pubkey::new_unique().to_string(),
+            "blockhash": "11111111111111111111111111111111",
+            "feeCalculator": {"lamportsPerSignature": "5000"},
+        },
+    });
+    let account = Account::decode(&parsed("nonce", nonce)).unwrap();
+    match account.parsed() {
+        Some(ParsedAccountData::Nonce(UiNonceState::Initialized(data))) => {
+            assert_eq!(data.fee_calculator.lamports_per_signature, "5000")
+        }
+        data => panic!("Unexpected data {:?}", data),
+    }
+
+    let account = Account::decode(&parsed("unknown", json!({"a": 1}))).unwrap();
+    match account.parsed() {
+        Some(ParsedAccountData::Other(other)) => assert_eq!(other.parsed, json!({"a": 1})),
+        data => panic!("Unexpected data {:?}", data),
+    }
+
+    assert!(matches!(
+        Account::decode(&parsed("stake", json!({"type": "nonsense"}))),
+        Err(SolanaClientError::Decode(_))
+    ));
+}
+
+#[tokio::test]
+async fn decoded_subscriptions() {
+    let (listener, url) = listen().await;
+    let raw = raw_account();
+    let pubkey = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let ui = UiAccount::encode(&pubkey, &raw, UiAccountEncoding::Base64Zstd, None, None);
+    let account = json!({"context": {"slot": 5}, "value": ui});
+    let token = parsed("spl-token-2022", token_account(&mint));
+    let keyed = json!({
+        "context": {"slot": 6},
+        "value": {"pubkey": pubkey.to_string(), "account": token},
+    });
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        for (id, method) in [(10, "accountSubscribe"), (11, "programSubscribe")] {
+            let req = recv_request(&mut ws).await;
+            assert_eq!(req["method"], method);
+            respond(&mut ws, &req["id"], id.into()).await;
+        }
+        notify(&mut ws, "accountNotification", 10, account).await;
+        notify(&mut ws, "programNotification", 11, keyed).await;
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let accounts = client
+        .account_subscribe_decoded(&pubkey, None)
+        .await
+        .unwrap();
+    let programs = client
+        .program_subscribe_decoded(&Pubkey::new_unique(), None)
+        .await
+        .unwrap();
+    futures::pin_mut!(accounts, programs);
+
+    let resp = accounts.next().await.unwrap().unwrap();
+    assert_eq!(resp.context.slot, 5);
+    assert_eq!(resp.value.bytes(), Some(&raw.data[..]));
+
+    let resp = programs.next().await.unwrap().unwrap();
+    assert_eq!(resp.value.pubkey, pubkey);
+    assert!(matches!(
+        resp.value.account.parsed(),
+        Some(ParsedAccountData::Token(TokenAccountType::Account(_)))
+    ));
+}
@@ -1,16 +1,15 @@
-use solana_account_decoder::UiAccount;
-use solana_client::rpc_response::Response;
+use futures::StreamExt;
 use solana_client_async::prelude::*;
 
 #[tokio::test]
 async fn account_subscribe() {
-    let mut client = ClientBuilder::new()
+    let client = ClientBuilder::new()
         .ws_url("wss://api.mainnet-beta.solana.com")
         .build()
         .await
         .unwrap();
 
-    client
+    let mut subscription = client
         .account_subscribe(
             &"5KKsLVU6TcbVDK4BS6K1DGDxnh4Q9xjYJ8XaDCG5t8ht"
                 .parse()
@@ -18,9 +17,7 @@ async fn account_subscribe() {
             None,
         )
         .await
-        .unwrap()
-        .await
         .unwrap();
 
-    client.recv::<Response<UiAccount>>().await.unwrap();
+    subscription.next().await.unwrap().unwrap();
 }
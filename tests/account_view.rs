@@ -0,0 +1,138 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use solana_client::rpc_response::Response;
+use solana_client_async::prelude::*;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc::UnboundedSender;
+
+const PROCESSED: u64 = 10;
+const CONFIRMED: u64 = 11;
+const SLOTS: u64 = 12;
+
+async fn serve_dual() -> (String, UnboundedSender<(u64, Value)>) {
+    // Processed is subscribed first, the ids tell the commitments apart
+    serve_mixed_subscriptions(&[
+        ("accountSubscribe", PROCESSED),
+        ("accountSubscribe", CONFIRMED),
+        ("slotsUpdatesSubscribe", SLOTS),
+    ])
+    .await
+}
+
+fn confirmed_slot(slot: u64) -> Value {
+    json!({"type": "optimisticConfirmation", "slot": slot, "timestamp": 1})
+}
+
+fn slot_of(resp: &Option<Response<impl Sized>>) -> Option<u64> {
+    resp.as_ref().map(|resp| resp.context.slot)
+}
+
+#[tokio::test]
+async fn dual_commitment() {
+    let (url, tx) = serve_dual().await;
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let mut sub = client
+        .account_subscribe_dual(&Pubkey::new_unique(), CommitmentConfig::confirmed(), None)
+        .await
+        .unwrap();
+
+    tx.send((PROCESSED, account_json(10, 1))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    assert_eq!(
+        (slot_of(&view.optimistic), slot_of(&view.settled)),
+        (Some(10), None)
+    );
+
+    tx.send((CONFIRMED, account_json(10, 1))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    assert_eq!(
+        (slot_of(&view.optimistic), slot_of(&view.settled)),
+        (Some(10), Some(10))
+    );
+    assert!(view.rolled_back.is_empty());
+
+    // A fork that never gets confirmed
+    tx.send((PROCESSED, account_json(11, 2))).unwrap();
+    sub.next().await.unwrap().unwrap();
+    tx.send((PROCESSED, account_json(12, 3))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    assert_eq!(slot_of(&view.optimistic), Some(12));
+
+    tx.send((CONFIRMED, account_json(13, 4))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    let rolled_back: Vec<_> = view.rolled_back.iter().map(|r| r.context.slot).collect();
+    assert_eq!(rolled_back, vec![11, 12]);
+    assert_eq!(view.optimistic, view.settled);
+    assert_eq!(view.optimistic.unwrap().value.lamports, 4);
+
+    // Already settled, only the next one is reported
+    tx.send((PROCESSED, account_json(13, 4))).unwrap();
+    tx.send((PROCESSED, account_json(14, 5))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    assert_eq!(
+        (slot_of(&view.optimistic), slot_of(&view.settled)),
+        (Some(14), Some(13))
+    );
+    assert!(view.rolled_back.is_empty());
+
+    // Several slots confirmed at once
+    tx.send((PROCESSED, account_json(15, 6))).unwrap();
+    sub.next().await.unwrap().unwrap();
+    tx.send((CONFIRMED, account_json(16, 6))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    assert!(view.rolled_back.is_empty());
+    assert_eq!(sub.view().settled.as_ref().unwrap().value.lamports, 6);
+}
+
+#[tokio::test]
+async fn settled_value_unchanged() {
+    let (url, tx) = serve_dual().await;
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let mut sub = client
+        .account_subscribe_dual(&Pubkey::new_unique(), CommitmentConfig::confirmed(), None)
+        .await
+        .unwrap();
+
+    tx.send((PROCESSED, account_json(10, 1))).unwrap();
+    sub.next().await.unwrap().unwrap();
+    tx.send((CONFIRMED, account_json(10, 1))).unwrap();
+    sub.next().await.unwrap().unwrap();
+
+    // A fork the settled commitment passes without any account notification
+    tx.send((PROCESSED, account_json(11, 2))).unwrap();
+    sub.next().await.unwrap().unwrap();
+    tx.send((PROCESSED, account_json(12, 3))).unwrap();
+    sub.next().await.unwrap().unwrap();
+
+    // Rooted slots are not followed for the confirmed commitment
+    let root = json!({"type": "root", "slot": 20, "timestamp": 1});
+    tx.send((SLOTS, root)).unwrap();
+    // Judged one slot late, the settled notification may lag the slot update
+    tx.send((SLOTS, confirmed_slot(11))).unwrap();
+    tx.send((SLOTS, confirmed_slot(13))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    let rolled_back: Vec<_> = view.rolled_back.iter().map(|r| r.context.slot).collect();
+    assert_eq!(rolled_back, vec![11]);
+    assert_eq!(slot_of(&view.optimistic), Some(12));
+
+    tx.send((SLOTS, confirmed_slot(14))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    let rolled_back: Vec<_> = view.rolled_back.iter().map(|r| r.context.slot).collect();
+    assert_eq!(rolled_back, vec![12]);
+    assert_eq!(view.optimistic, view.settled);
+    assert_eq!(view.optimistic.unwrap().value.lamports, 1);
+
+    // Matching the settled value, not rolled back
+    tx.send((PROCESSED, account_json(15, 1))).unwrap();
+    sub.next().await.unwrap().unwrap();
+    tx.send((SLOTS, confirmed_slot(15))).unwrap();
+    tx.send((SLOTS, confirmed_slot(16))).unwrap();
+    tx.send((PROCESSED, account_json(17, 7))).unwrap();
+    let view = sub.next().await.unwrap().unwrap();
+    assert!(view.rolled_back.is_empty());
+    assert_eq!(slot_of(&view.optimistic), Some(17));
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::Value;
+use solana_client_async::prelude::*;
+use std::time::Duration;
+use tokio::time::timeout;
+
+async fn drain(backpressure: Backpressure) -> (Vec<u64>, u64) {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 7.into()).await;
+        for slot in 100..103 {
+            notify(&mut ws, "slotNotification", 7, slot_info(slot)).await;
+        }
+        // Answered after the notifications, so they are all queued once it resolves
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 0.into()).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let mut slots = client
+        .with_backpressure(backpressure)
+        .slot_subscribe()
+        .await
+        .unwrap();
+    let _: u64 = client
+        .request("getSlot", &Value::Null)
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+    let ws = server.await.unwrap();
+    drop(ws);
+    drop(client);
+
+    let mut received = vec![];
+    if let Some(slot) = slots.next().await {
+        received.push(slot.unwrap().slot);
+    }
+    (received, slots.dropped())
+}
+
+#[tokio::test]
+async fn coalesce_latest() {
+    assert_eq!(drain(Backpressure::CoalesceLatest).await, (vec![102], 2));
+}
+
+#[tokio::test]
+async fn drop_newest() {
+    assert_eq!(drain(Backpressure::DropNewest(1)).await, (vec![100], 2));
+}
+
+#[tokio::test]
+async fn drop_oldest() {
+    assert_eq!(drain(Backpressure::DropOldest(2)).await, (vec![101], 1));
+}
+
+#[tokio::test]
+async fn block() {
+    let (listener, url) = listen().await;
+
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 7.into()).await;
+        for slot in 100..102 {
+            notify(&mut ws, "slotNotification", 7, slot_info(slot)).await;
+        }
+        // Only answered on the same connection
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 0.into()).await;
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&url)
+        .ping_every(1)
+        .stale_timeout(Duration::from_millis(1500))
+        .build()
+        .await
+        .unwrap();
+    let mut slots = client
+        .with_backpressure(Backpressure::Block(1))
+        .slot_subscribe()
+        .await
+        .unwrap();
+
+    // The background process waits for the consumer to make room for 101, for longer
+    // than the stale timeout, with nothing else to read afterwards
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    for slot in 100..102 {
+        assert_eq!(slots.next().await.unwrap().unwrap().slot, slot);
+    }
+    assert_eq!(slots.dropped(), 0);
+
+    // The connection was not considered stale
+    let get_slot = async {
+        let awaiter = client.request::<_, u64>("getSlot", &Value::Null).await;
+        awaiter.unwrap().await.unwrap()
+    };
+    assert_eq!(timeout(Duration::from_secs(5), get_slot).await.unwrap(), 0);
+}
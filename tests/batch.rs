@@ -0,0 +1,81 @@
+mod common;
+
+use common::*;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use solana_client_async::prelude::*;
+use tungstenite::Message;
+
+#[tokio::test]
+async fn ws_batch() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let reqs = recv_request(&mut ws).await;
+        let reqs = reqs.as_array().unwrap();
+        assert_eq!(reqs.len(), 3);
+
+        let resps: Vec<_> = reqs
+            .iter()
+            .rev()
+            .map(|req| match req["method"].as_str().unwrap() {
+                "getSlot" => rpc_result(req, 100.into()),
+                "slotSubscribe" => rpc_result(req, 7.into()),
+                _ => rpc_error(req, -32601, "Method not found"),
+            })
+            .collect();
+        ws.send(Message::Text(Value::from(resps).to_string()))
+            .await
+            .unwrap();
+        notify(&mut ws, "slotNotification", 7, slot_info(100)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let mut batch = client.batch();
+    let slot = batch.request::<_, u64>("getSlot", &Value::Null).unwrap();
+    let slots = batch
+        .subscribe::<_, solana_client::rpc_response::SlotInfo>("slotSubscribe", &Value::Null)
+        .unwrap();
+    let missing = batch.request::<_, u64>("getFoo", &Value::Null).unwrap();
+    assert_eq!(batch.len(), 3);
+    batch.send().await.unwrap();
+
+    assert_eq!(slot.await.unwrap(), 100);
+    let mut slots = slots.await.unwrap();
+    assert_eq!(slots.next().await.unwrap().unwrap().slot, 100);
+    assert!(matches!(
+        missing.await,
+        Err(SolanaClientError::RpcError { code: -32601, .. })
+    ));
+
+    let _ws = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn http_batch() {
+    let url = serve_http(|reqs| {
+        let resps: Vec<_> = reqs
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|req| rpc_result(req, req["params"][0].clone()))
+            .collect();
+        json!(resps)
+    })
+    .await;
+
+    let client = ClientBuilder::new().url(&url).build_http().unwrap();
+
+    let mut batch = client.batch();
+    let awaiters: Vec<_> = (0..10u64)
+        .map(|i| batch.request::<_, u64>("echo", &[i]).unwrap())
+        .collect();
+    batch.send().await.unwrap();
+
+    for (i, awaiter) in awaiters.into_iter().enumerate() {
+        assert_eq!(awaiter.await.unwrap(), i as u64);
+    }
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::*;
+use serde_json::json;
+use solana_client_async::prelude::*;
+use solana_sdk::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Answer the batches with a new blockhash, 10 blocks later, each time.
+async fn serve_blockhashes(blockhashes: Vec<Hash>) -> String {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    serve_http(move |reqs| {
+        let n = fetches.fetch_add(1, Ordering::SeqCst);
+        let resps: Vec<_> = reqs
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|req| match req["method"].as_str().unwrap() {
+                "getLatestBlockhash" => {
+                    assert_eq!(req["params"][0]["commitment"], "confirmed");
+                    let value = json!({
+                        "blockhash": blockhashes[n.min(blockhashes.len() - 1)].to_string(),
+                        "lastValidBlockHeight": 250 + n * 10,
+                    });
+                    rpc_result(req, json!({"context": {"slot": 1000 + n}, "value": value}))
+                }
+                "getBlockHeight" => rpc_result(req, (100 + n * 10).into()),
+                _ => rpc_error(req, -32601, "Method not found"),
+            })
+            .collect();
+        json!(resps)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn refresh_on_slot() {
+    let blockhashes = vec![Hash::new_unique(), Hash::new_unique()];
+    let url = serve_blockhashes(blockhashes.clone()).await;
+    let (ws_url, slot_tx) = serve_subscriptions("slotSubscribe", &[10]).await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let config = BlockhashTrackerConfig {
+        min_refresh: Duration::ZERO,
+        max_refresh: Duration::from_secs(60),
+        ..Default::default()
+    };
+    let tracker = BlockhashTracker::spawn(&client, &http, config)
+        .await
+        .unwrap();
+    assert_eq!(
+        tracker.latest(),
+        LatestBlockhash {
+            blockhash: blockhashes[0],
+            last_valid_block_height: 250,
+            block_height: 100,
+            slot: 1000,
+        }
+    );
+
+    let mut watch = tracker.watch();
+    slot_tx.send((10, slot_info(1001))).unwrap();
+    timeout(Duration::from_secs(5), watch.changed())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let latest = tracker.latest();
+    assert_eq!(latest.blockhash, blockhashes[1]);
+    assert_eq!(latest.last_valid_block_height, 260);
+    assert_eq!(latest.block_height, 110);
+}
+
+#[tokio::test]
+async fn periodic_refresh() {
+    let blockhashes = vec![Hash::new_unique(), Hash::new_unique()];
+    let url = serve_blockhashes(blockhashes.clone()).await;
+    let (ws_url, _slot_tx) = serve_subscriptions("slotSubscribe", &[10]).await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let config = BlockhashTrackerConfig {
+        max_refresh: Duration::from_millis(100),
+        ..Default::default()
+    };
+    let tracker = BlockhashTracker::spawn(&client, &http, config)
+        .await
+        .unwrap();
+    let mut watch = tracker.watch();
+
+    // No slot arrives, still refreshed
+    timeout(Duration::from_secs(5), watch.changed())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tracker.latest().blockhash, blockhashes[1]);
+}
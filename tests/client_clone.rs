@@ -0,0 +1,44 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use solana_client_async::prelude::*;
+
+fn assert_send_sync<T: Send + Sync + Clone>() {}
+
+#[tokio::test]
+async fn client_clone() {
+    assert_send_sync::<Client>();
+
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        for server_id in [7, 8] {
+            let req = recv_request(&mut ws).await;
+            assert_eq!(req["method"], "slotSubscribe");
+            respond(&mut ws, &req["id"], server_id.into()).await;
+        }
+        notify(&mut ws, "slotNotification", 7, slot_info(100)).await;
+        notify(&mut ws, "slotNotification", 8, slot_info(100)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let tasks: Vec<_> = (0..2)
+        .map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let mut slots = client.slot_subscribe().await.unwrap();
+                slots.next().await.unwrap().unwrap().slot
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), 100);
+    }
+
+    let _ws = server.await.unwrap();
+}
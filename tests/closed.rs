@@ -0,0 +1,51 @@
+mod common;
+
+use common::*;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use solana_client_async::prelude::*;
+use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use tungstenite::Message;
+
+#[tokio::test]
+async fn closed() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 7.into()).await;
+        // Never answered
+        recv_request(&mut ws).await;
+
+        let frame = CloseFrame {
+            code: CloseCode::Away,
+            reason: "maintenance".into(),
+        };
+        ws.send(Message::Close(Some(frame))).await.unwrap();
+        ws
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&url)
+        .reconnect_policy(ReconnectPolicy::never())
+        .build()
+        .await
+        .unwrap();
+
+    let mut slots = client.slot_subscribe().await.unwrap();
+    let awaiter = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap();
+
+    let is_maintenance = |e: &SolanaClientError| matches!(e, SolanaClientError::WsClosed(Some(reason)) if reason.contains("maintenance"));
+
+    assert!(is_maintenance(&awaiter.await.unwrap_err()));
+    assert!(is_maintenance(&slots.next().await.unwrap().unwrap_err()));
+    assert!(slots.next().await.is_none());
+    assert!(is_maintenance(&client.closed().await));
+    assert!(is_maintenance(&client.clone().closed().await));
+
+    let _ws = server.await.unwrap();
+}
@@ -0,0 +1,220 @@
+#![allow(dead_code)]
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{from_str, json, Value};
+use solana_sdk::pubkey::Pubkey;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{accept_async, WebSocketStream};
+use tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+use tungstenite::Message;
+
+pub type ServerStream = WebSocketStream<TcpStream>;
+
+/// Bind a scripted websocket server on a random local port.
+pub async fn listen() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    (listener, url)
+}
+
+pub async fn accept(listener: &TcpListener) -> ServerStream {
+    let (stream, _) = listener.accept().await.unwrap();
+    accept_async(stream).await.unwrap()
+}
+
+/// Accept a connection and return the headers of the handshake request.
+pub async fn accept_with_headers(listener: &TcpListener) -> (ServerStream, http::HeaderMap) {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut headers = http::HeaderMap::new();
+    let ws = tokio_tungstenite::accept_hdr_async(stream, CaptureHeaders(&mut headers))
+        .await
+        .unwrap();
+    (ws, headers)
+}
+
+/// Handshake callback keeping the request headers.
+struct CaptureHeaders<'a>(&'a mut http::HeaderMap);
+
+impl Callback for CaptureHeaders<'_> {
+    fn on_request(self, req: &Request, resp: Response) -> Result<Response, ErrorResponse> {
+        *self.0 = req.headers().clone();
+        Ok(resp)
+    }
+}
+
+/// Receive the next JSON-RPC request, skipping control frames.
+pub async fn recv_request(ws: &mut ServerStream) -> Value {
+    loop {
+        match ws.next().await.unwrap().unwrap() {
+            Message::Text(text) => return from_str(&text).unwrap(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            msg => panic!("Unexpected message {:?}", msg),
+        }
+    }
+}
+
+pub async fn respond(ws: &mut ServerStream, id: &Value, result: Value) {
+    let resp = json!({"jsonrpc": "2.0", "id": id, "result": result});
+    ws.send(Message::Text(resp.to_string())).await.unwrap();
+}
+
+pub async fn notify(ws: &mut ServerStream, method: &str, subscription: u64, result: Value) {
+    let notif = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": {"result": result, "subscription": subscription},
+    });
+    ws.send(Message::Text(notif.to_string())).await.unwrap();
+}
+
+pub fn slot_info(slot: u64) -> Value {
+    json!({"slot": slot, "parent": slot - 1, "root": slot - 32})
+}
+
+/// A base64 encoded account without data.
+pub fn ui_account_json(lamports: u64) -> Value {
+    json!({
+        "lamports": lamports,
+        "data": ["", "base64"],
+        "owner": Pubkey::default().to_string(),
+        "executable": false,
+        "rentEpoch": 0,
+    })
+}
+
+/// An account notification.
+pub fn account_json(slot: u64, lamports: u64) -> Value {
+    json!({"context": {"slot": slot}, "value": ui_account_json(lamports)})
+}
+
+/// A program notification.
+pub fn keyed_account_json(pubkey: &Pubkey, slot: u64, lamports: u64) -> Value {
+    json!({
+        "context": {"slot": slot},
+        "value": {"pubkey": pubkey.to_string(), "account": ui_account_json(lamports)},
+    })
+}
+
+/// Answer the subscriptions to `method` with `ids` in order, sent alone or batched,
+/// then forward the notifications of the channel, as (subscription id, result).
+pub async fn serve_subscriptions(
+    method: &'static str,
+    ids: &[u64],
+) -> (String, mpsc::UnboundedSender<(u64, Value)>) {
+    let subs: Vec<_> = ids.iter().map(|&id| (method, id)).collect();
+    serve_mixed_subscriptions(&subs).await
+}
+
+/// Like `serve_subscriptions`, expecting the subscriptions in the order of `subs`,
+/// as (method, id).
+pub async fn serve_mixed_subscriptions(
+    subs: &[(&'static str, u64)],
+) -> (String, mpsc::UnboundedSender<(u64, Value)>) {
+    let (listener, url) = listen().await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let notifications: std::collections::HashMap<_, _> = subs
+        .iter()
+        .map(|&(method, id)| (id, method.replace("Subscribe", "Notification")))
+        .collect();
+    let subs = subs.to_vec();
+
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let mut subs = subs.into_iter();
+        while subs.len() > 0 {
+            let req = recv_request(&mut ws).await;
+            let batched = req.is_array();
+            let reqs = match req {
+                Value::Array(reqs) => reqs,
+                req => vec![req],
+            };
+            let resps: Vec<_> = reqs
+                .iter()
+                .map(|req| {
+                    let (method, id) = subs.next().unwrap();
+                    assert_eq!(req["method"], method);
+                    rpc_result(req, id.into())
+                })
+                .collect();
+            let resp = match batched {
+                true => Value::from(resps),
+                false => resps[0].clone(),
+            };
+            ws.send(Message::Text(resp.to_string())).await.unwrap();
+        }
+        while let Some((sub, value)) = rx.recv().await {
+            notify(&mut ws, &notifications[&sub], sub, value).await;
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    (url, tx)
+}
+
+/// Serve JSON-RPC over HTTP on a random local port, answering each request body with `handler`.
+pub async fn serve_http<F>(handler: F) -> String
+where
+    F: Fn(Value) -> Value + Send + Sync + 'static,
+{
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handler = Arc::new(handler);
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![];
+                loop {
+                    let header_end = loop {
+                        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break i + 4;
+                        }
+                        let mut chunk = [0; 4096];
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                    };
+                    let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+                    let len: usize = headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .map(|len| len.trim().parse().unwrap())
+                        .unwrap_or(0);
+                    while buf.len() < header_end + len {
+                        let mut chunk = [0; 4096];
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+                    let body: Vec<u8> = buf.drain(..header_end + len).skip(header_end).collect();
+
+                    let resp = handler(from_str(std::str::from_utf8(&body).unwrap()).unwrap());
+                    let resp = resp.to_string();
+                    let resp = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                        resp.len(),
+                        resp
+                    );
+                    stream.write_all(resp.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+
+    url
+}
+
+pub fn rpc_result(req: &Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": req["id"], "result": result})
+}
+
+pub fn rpc_error(req: &Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": req["id"], "error": {"code": code, "message": message}})
+}
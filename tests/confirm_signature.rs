@@ -0,0 +1,116 @@
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+use solana_client::rpc_config::RpcSignatureSubscribeConfig;
+use solana_client::rpc_response::RpcSignatureResult;
+use solana_client_async::prelude::*;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use std::time::Duration;
+
+#[tokio::test]
+async fn confirm_signature() {
+    let (listener, url) = listen().await;
+    let signature = Signature::new_unique();
+    let expected = signature.to_string();
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "signatureSubscribe");
+        assert_eq!(req["params"][0], expected);
+        assert_eq!(req["params"][1]["commitment"], "confirmed");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        let result = json!({"context": {"slot": 100}, "value": {"err": null}});
+        notify(&mut ws, "signatureNotification", 10, result).await;
+
+        // Auto-cancelled by the server, so no unsubscribe is sent
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "getSlot");
+        respond(&mut ws, &req["id"], 100.into()).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let result = client
+        .confirm_signature(
+            &signature,
+            CommitmentConfig::confirmed(),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(result, RpcSignatureResult::ProcessedSignature(r) if r.err.is_none()));
+
+    let slot = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+    assert_eq!(slot, 100);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_received_signature() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["params"][1]["enableReceivedNotification"], true);
+        respond(&mut ws, &req["id"], 10.into()).await;
+        let result = json!({"context": {"slot": 100}, "value": "receivedSignature"});
+        notify(&mut ws, "signatureNotification", 10, result).await;
+
+        // Still live on the server, so it gets unsubscribed
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "signatureUnsubscribe");
+        assert_eq!(req["params"][0], 10);
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let config = RpcSignatureSubscribeConfig {
+        commitment: None,
+        enable_received_notification: Some(true),
+    };
+    let result = client
+        .confirm_signature_with_config(&Signature::new_unique(), config, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert!(matches!(result, RpcSignatureResult::ReceivedSignature(_)));
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_signature_timeout() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 10.into()).await;
+
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "signatureUnsubscribe");
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let error = client
+        .confirm_signature(
+            &Signature::new_unique(),
+            CommitmentConfig::finalized(),
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, SolanaClientError::Timeout));
+
+    server.await.unwrap();
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use solana_client_async::prelude::*;
+use std::future::pending;
+
+#[tokio::test]
+async fn failover_on_close() {
+    let (primary, primary_url) = listen().await;
+    let (backup, backup_url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let (mut ws, headers) = accept_with_headers(&primary).await;
+        assert_eq!(headers["x-common"], "common");
+        assert_eq!(headers["x-api-key"], "primary");
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 10.into()).await;
+        notify(&mut ws, "slotNotification", 10, slot_info(100)).await;
+        drop(ws);
+
+        let (mut ws, headers) = accept_with_headers(&backup).await;
+        assert_eq!(headers["x-common"], "common");
+        assert_eq!(headers["x-api-key"], "backup");
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 20.into()).await;
+        notify(&mut ws, "slotNotification", 20, slot_info(101)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&primary_url)
+        .header("x-common", "common")
+        .header("x-api-key", "primary")
+        .failover(Endpoint::new(&backup_url).header("x-api-key", "backup"))
+        .build()
+        .await
+        .unwrap();
+
+    let mut subscription = client.slot_subscribe().await.unwrap();
+    let subscription_id = subscription.id();
+
+    let slot = subscription.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 100);
+
+    let slot = subscription.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 101);
+    assert_eq!(subscription.id(), subscription_id);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn failover_on_ping_timeout() {
+    let (primary, primary_url) = listen().await;
+    let (backup, backup_url) = listen().await;
+
+    tokio::spawn(async move {
+        let mut ws = accept(&primary).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 10.into()).await;
+        // Stop reading, so the pings are never answered
+        pending::<()>().await;
+        drop(ws);
+    });
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&backup).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 20.into()).await;
+        notify(&mut ws, "slotNotification", 20, slot_info(101)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&primary_url)
+        .ping_every(1)
+        .failover(&Endpoint::new(&backup_url))
+        .build()
+        .await
+        .unwrap();
+
+    let mut subscription = client.slot_subscribe().await.unwrap();
+
+    let slot = subscription.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 101);
+
+    server.await.unwrap();
+}
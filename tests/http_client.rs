@@ -0,0 +1,53 @@
+mod common;
+
+use common::*;
+use serde_json::json;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_client_async::prelude::*;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_transaction;
+
+#[tokio::test]
+async fn http_client() {
+    let payer = Keypair::new();
+    let tx = system_transaction::transfer(&payer, &payer.pubkey(), 1, Hash::default());
+    let signature = tx.signatures[0];
+
+    let url = serve_http(move |req| match req["method"].as_str().unwrap() {
+        "getSlot" => rpc_result(&req, 100.into()),
+        "getLatestBlockhash" => rpc_result(
+            &req,
+            json!({
+                "context": {"slot": 100},
+                "value": {"blockhash": Hash::default().to_string(), "lastValidBlockHeight": 250}
+            }),
+        ),
+        "sendTransaction" => {
+            assert_eq!(req["params"][1]["encoding"], "base64");
+            rpc_result(&req, signature.to_string().into())
+        }
+        _ => rpc_error(&req, -32601, "Method not found"),
+    })
+    .await;
+
+    let client = ClientBuilder::new().url(&url).build_http().unwrap();
+
+    assert_eq!(client.get_slot(None).await.unwrap(), 100);
+
+    let blockhash = client.get_latest_blockhash(None).await.unwrap();
+    assert_eq!(blockhash.context.slot, 100);
+    assert_eq!(blockhash.value.last_valid_block_height, 250);
+
+    let sent = client
+        .send_transaction(&tx, RpcSendTransactionConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(sent, signature);
+
+    let err = client.get_block(100, None).await.unwrap_err();
+    assert!(matches!(
+        err,
+        SolanaClientError::RpcError { code: -32601, .. }
+    ));
+}
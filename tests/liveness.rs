@@ -0,0 +1,65 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use solana_client_async::prelude::*;
+use std::future::pending;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+#[tokio::test]
+async fn latency() {
+    let (listener, url) = listen().await;
+
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        // Reading answers the pings
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&url)
+        .ping_every(1)
+        .build()
+        .await
+        .unwrap();
+
+    let latency = timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(latency) = client.latency() {
+                return latency;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert!(latency < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn stale() {
+    let (listener, url) = listen().await;
+
+    tokio::spawn(async move {
+        let ws = accept(&listener).await;
+        // Never read, so the pings are never answered
+        pending::<()>().await;
+        drop(ws);
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&url)
+        .ping_every(1)
+        .stale_timeout(Duration::from_millis(1500))
+        .reconnect_policy(ReconnectPolicy::never())
+        .build()
+        .await
+        .unwrap();
+
+    let error = timeout(Duration::from_secs(5), client.closed())
+        .await
+        .unwrap();
+    assert!(matches!(error, SolanaClientError::Stale(_)));
+    assert!(client.latency().is_none());
+}
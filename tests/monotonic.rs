@@ -0,0 +1,86 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::Value;
+use solana_client_async::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+
+/// Answer a single subscription, then send the notifications.
+async fn serve(method: &'static str, notifications: Vec<Value>) -> String {
+    let (url, tx) = serve_subscriptions(method, &[10]).await;
+    for value in notifications {
+        tx.send((10, value)).unwrap();
+    }
+    url
+}
+
+#[tokio::test]
+async fn account_regressions() {
+    let notifications = [5, 3, 5, 4, 6]
+        .into_iter()
+        .map(|slot| account_json(slot, slot))
+        .collect();
+    let url = serve("accountSubscribe", notifications).await;
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let mut sub = client
+        .with_monotonic_slots(true)
+        .account_subscribe(&Pubkey::new_unique(), None)
+        .await
+        .unwrap();
+
+    let mut slots = vec![];
+    for _ in 0..3 {
+        slots.push(sub.next().await.unwrap().unwrap().context.slot);
+    }
+    assert_eq!(slots, vec![5, 5, 6]);
+    assert_eq!(sub.rejected(), 2);
+}
+
+#[tokio::test]
+async fn program_regressions_per_pubkey() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let notifications = [(a, 10), (b, 5), (a, 9), (b, 6)]
+        .iter()
+        .map(|(pubkey, slot)| keyed_account_json(pubkey, *slot, *slot))
+        .collect();
+    let url = serve("programSubscribe", notifications).await;
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let mut sub = client
+        .with_monotonic_slots(true)
+        .program_subscribe(&Pubkey::new_unique(), None)
+        .await
+        .unwrap();
+
+    let mut received = vec![];
+    for _ in 0..3 {
+        let resp = sub.next().await.unwrap().unwrap();
+        received.push((resp.value.pubkey, resp.context.slot));
+    }
+    assert_eq!(
+        received,
+        vec![(a.to_string(), 10), (b.to_string(), 5), (b.to_string(), 6)]
+    );
+    assert_eq!(sub.rejected(), 1);
+}
+
+#[tokio::test]
+async fn disabled_by_default() {
+    let notifications = [5, 3]
+        .into_iter()
+        .map(|slot| account_json(slot, slot))
+        .collect();
+    let url = serve("accountSubscribe", notifications).await;
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let mut sub = client
+        .account_subscribe(&Pubkey::new_unique(), None)
+        .await
+        .unwrap();
+
+    assert_eq!(sub.next().await.unwrap().unwrap().context.slot, 5);
+    assert_eq!(sub.next().await.unwrap().unwrap().context.slot, 3);
+    assert_eq!(sub.rejected(), 0);
+}
@@ -1,15 +1,15 @@
-use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use futures::StreamExt;
 use solana_client_async::prelude::*;
 
 #[tokio::test]
 async fn program_subscribe() {
-    let mut client = ClientBuilder::new()
+    let client = ClientBuilder::new()
         .ws_url("wss://api.mainnet-beta.solana.com")
         .build()
         .await
         .unwrap();
 
-    client
+    let mut subscription = client
         .program_subscribe(
             &"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
                 .parse()
@@ -17,9 +17,7 @@ async fn program_subscribe() {
             None,
         )
         .await
-        .unwrap()
-        .await
         .unwrap();
 
-    client.recv::<Response<RpcKeyedAccount>>().await.unwrap();
+    subscription.next().await.unwrap().unwrap();
 }
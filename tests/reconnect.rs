@@ -0,0 +1,79 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::Value;
+use solana_client_async::prelude::*;
+
+#[tokio::test]
+async fn reconnect() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        notify(&mut ws, "slotNotification", 10, slot_info(100)).await;
+        drop(ws);
+
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        assert_eq!(req["params"], Value::Null);
+        respond(&mut ws, &req["id"], 20.into()).await;
+        notify(&mut ws, "slotNotification", 20, slot_info(101)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let mut subscription = client.slot_subscribe().await.unwrap();
+    let subscription_id = subscription.id();
+
+    let slot = subscription.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 100);
+
+    let slot = subscription.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 101);
+    assert_eq!(subscription.id(), subscription_id);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn unsubscribe_in_flight() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotUnsubscribe");
+        // Lost with the connection before being answered
+        drop(ws);
+
+        // The old server id is not resent, it may belong to another subscription now
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "rootSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let slots = client.slot_subscribe().await.unwrap();
+
+    let unsubscribed = client
+        .slot_unsubscribe(slots.id())
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+    assert!(unsubscribed);
+
+    client.root_subscribe().await.unwrap();
+    let _ws = server.await.unwrap();
+}
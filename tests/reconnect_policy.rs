@@ -0,0 +1,48 @@
+mod common;
+
+use common::*;
+use solana_client_async::prelude::*;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+#[test]
+fn backoff() {
+    let mut policy = ReconnectPolicy::new();
+    policy
+        .initial_backoff(Duration::from_millis(100))
+        .max_backoff(Duration::from_secs(1))
+        .multiplier(3.);
+
+    assert_eq!(policy.backoff(0), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(900));
+    assert_eq!(policy.backoff(3), Duration::from_secs(1));
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn give_up() {
+    let (listener, url) = listen().await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut policy = ReconnectPolicy::new();
+    policy
+        .initial_backoff(Duration::from_millis(10))
+        .max_attempts(2)
+        .on_give_up(move |e| {
+            let _ = tx.send(e.to_string());
+        });
+
+    let server = tokio::spawn(async move { accept(&listener).await });
+
+    let _client = ClientBuilder::new()
+        .ws_url(&url)
+        .reconnect_policy(policy)
+        .build()
+        .await
+        .unwrap();
+
+    drop(server.await.unwrap());
+
+    let reason = rx.recv().await.unwrap();
+    assert!(!reason.is_empty());
+}
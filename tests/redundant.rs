@@ -0,0 +1,181 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use solana_client_async::prelude::*;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::sleep;
+
+#[tokio::test]
+async fn redundant() {
+    let (primary, primary_url) = listen().await;
+    let (backup, backup_url) = listen().await;
+    let (backup_go_tx, backup_go) = oneshot::channel::<()>();
+    let (primary_close_tx, primary_close) = oneshot::channel::<()>();
+    let (backup_more_tx, backup_more) = oneshot::channel::<()>();
+    let (backup_close_tx, backup_close) = oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        let mut ws = accept(&primary).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        notify(&mut ws, "slotNotification", 10, slot_info(100)).await;
+        primary_close.await.unwrap();
+    });
+
+    tokio::spawn(async move {
+        let mut ws = accept(&backup).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 20.into()).await;
+        backup_go.await.unwrap();
+        // A copy of the update the primary already delivered
+        notify(&mut ws, "slotNotification", 20, slot_info(100)).await;
+        notify(&mut ws, "slotNotification", 20, slot_info(101)).await;
+        backup_more.await.unwrap();
+        notify(&mut ws, "slotNotification", 20, slot_info(102)).await;
+        backup_close.await.unwrap();
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&primary_url)
+        .failover(&Endpoint::new(&backup_url))
+        .reconnect_policy(ReconnectPolicy::never())
+        .build_redundant()
+        .await
+        .unwrap();
+    assert_eq!(client.clients().len(), 2);
+
+    let mut slots = client.slot_subscribe().await.unwrap();
+
+    let update = slots.next().await.unwrap().unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (0, 100));
+
+    backup_go_tx.send(()).unwrap();
+    let update = slots.next().await.unwrap().unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (1, 101));
+    assert_eq!(slots.wins(), &[1, 1]);
+
+    // The primary failing does not end the stream
+    primary_close_tx.send(()).unwrap();
+    backup_more_tx.send(()).unwrap();
+    let update = slots.next().await.unwrap().unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (1, 102));
+    backup_close_tx.send(()).unwrap();
+
+    // Once every endpoint failed, the error is yielded
+    assert!(slots.next().await.unwrap().is_err());
+    assert!(slots.next().await.is_none());
+}
+
+#[tokio::test]
+async fn endpoint_down() {
+    // Nothing listens on the primary at startup
+    let (primary, primary_url) = listen().await;
+    let primary_addr = primary.local_addr().unwrap();
+    drop(primary);
+    let (backup, backup_url) = listen().await;
+
+    tokio::spawn(async move {
+        let mut ws = accept(&backup).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 20.into()).await;
+        notify(&mut ws, "slotNotification", 20, slot_info(100)).await;
+        while ws.next().await.is_some() {}
+    });
+
+    let mut policy = ReconnectPolicy::new();
+    policy.initial_backoff(Duration::from_millis(50));
+    let client = ClientBuilder::new()
+        .ws_url(&primary_url)
+        .failover(&Endpoint::new(&backup_url))
+        .reconnect_policy(policy)
+        .build_redundant()
+        .await
+        .unwrap();
+    assert!(client.clients()[0].is_none());
+    assert!(client.clients()[1].is_some());
+
+    let mut slots = client.slot_subscribe().await.unwrap();
+    let update = slots.next().await.unwrap().unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (1, 100));
+
+    // The primary comes up later and joins the subscription
+    let primary = tokio::net::TcpListener::bind(primary_addr).await.unwrap();
+    tokio::spawn(async move {
+        let mut ws = accept(&primary).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        notify(&mut ws, "slotNotification", 10, slot_info(101)).await;
+        while ws.next().await.is_some() {}
+    });
+
+    let update = tokio::time::timeout(Duration::from_secs(5), slots.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (0, 101));
+    assert_eq!(slots.wins(), &[1, 1]);
+    assert!(client.clients()[0].is_some());
+
+    // Fails only if every endpoint is down
+    let (down, down_url) = listen().await;
+    drop(down);
+    assert!(ClientBuilder::new()
+        .ws_url(&down_url)
+        .build_redundant()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn earliest_wins() {
+    let (primary, primary_url) = listen().await;
+    let (backup, backup_url) = listen().await;
+    let (backup_go_tx, backup_go) = oneshot::channel::<()>();
+    let (primary_go_tx, primary_go) = oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        let mut ws = accept(&primary).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 10.into()).await;
+        primary_go.await.unwrap();
+        notify(&mut ws, "slotNotification", 10, slot_info(100)).await;
+        notify(&mut ws, "slotNotification", 10, slot_info(101)).await;
+        while ws.next().await.is_some() {}
+    });
+
+    tokio::spawn(async move {
+        let mut ws = accept(&backup).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 20.into()).await;
+        backup_go.await.unwrap();
+        notify(&mut ws, "slotNotification", 20, slot_info(100)).await;
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&primary_url)
+        .failover(&Endpoint::new(&backup_url))
+        .build_redundant()
+        .await
+        .unwrap();
+    let mut slots = client.slot_subscribe().await.unwrap();
+
+    // Both copies of 100 are queued before the stream is polled, the backup's first
+    backup_go_tx.send(()).unwrap();
+    sleep(Duration::from_millis(100)).await;
+    primary_go_tx.send(()).unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let update = slots.next().await.unwrap().unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (1, 100));
+    let update = slots.next().await.unwrap().unwrap();
+    assert_eq!((update.endpoint, update.value.slot), (0, 101));
+    assert_eq!(slots.wins(), &[1, 1]);
+}
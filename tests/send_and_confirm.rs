@@ -0,0 +1,198 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use solana_client_async::prelude::*;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_transaction;
+use solana_sdk::transaction::TransactionError;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Serve the signature subscription, sending `result` once the transaction is sent.
+async fn serve_ws(result: Option<Value>) -> (String, Arc<AtomicBool>, Arc<Notify>) {
+    let (listener, url) = listen().await;
+    let subscribed = Arc::new(AtomicBool::new(false));
+    let sent = Arc::new(Notify::new());
+
+    let (subscribed_, sent_) = (subscribed.clone(), sent.clone());
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "signatureSubscribe");
+        assert_eq!(req["params"][1]["commitment"], "confirmed");
+        respond(&mut ws, &req["id"], 10.into()).await;
+        subscribed_.store(true, Ordering::SeqCst);
+
+        sent_.notified().await;
+        if let Some(result) = result {
+            notify(&mut ws, "signatureNotification", 10, result).await;
+        }
+        // Keep the connection open
+        while ws.next().await.is_some() {}
+    });
+
+    (url, subscribed, sent)
+}
+
+#[tokio::test]
+async fn confirmed() {
+    let payer = Keypair::new();
+    let tx = system_transaction::transfer(&payer, &payer.pubkey(), 1, Hash::new_unique());
+    let signature = tx.signatures[0];
+
+    let result = json!({"context": {"slot": 42}, "value": {"err": null}});
+    let (ws_url, subscribed, sent) = serve_ws(Some(result)).await;
+    let url = serve_http(move |req| match req["method"].as_str().unwrap() {
+        "sendTransaction" => {
+            assert!(subscribed.load(Ordering::SeqCst));
+            sent.notify_one();
+            rpc_result(&req, signature.to_string().into())
+        }
+        _ => rpc_error(&req, -32601, "Method not found"),
+    })
+    .await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let confirmation = client
+        .send_and_confirm(&http, &tx, SendAndConfirmConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(confirmation, Confirmation::Confirmed { slot: 42 });
+}
+
+#[tokio::test]
+async fn failed() {
+    let payer = Keypair::new();
+    let tx = system_transaction::transfer(&payer, &payer.pubkey(), 1, Hash::new_unique());
+    let signature = tx.signatures[0];
+
+    let result = json!({
+        "context": {"slot": 42},
+        "value": {"err": {"InstructionError": [0, {"Custom": 1}]}},
+    });
+    let (ws_url, _, sent) = serve_ws(Some(result)).await;
+    let url = serve_http(move |req| {
+        sent.notify_one();
+        rpc_result(&req, signature.to_string().into())
+    })
+    .await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let confirmation = client
+        .send_and_confirm(&http, &tx, SendAndConfirmConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        confirmation,
+        Confirmation::Failed {
+            slot: 42,
+            err: TransactionError::InstructionError(0, InstructionError::Custom(1)),
+        }
+    );
+}
+
+#[tokio::test]
+async fn expired() {
+    let payer = Keypair::new();
+    let tx = system_transaction::transfer(&payer, &payer.pubkey(), 1, Hash::new_unique());
+    let signature = tx.signatures[0];
+
+    let (ws_url, _, sent) = serve_ws(None).await;
+    let sends = Arc::new(AtomicU64::new(0));
+    let block_height = Arc::new(AtomicU64::new(100));
+
+    let sends_ = sends.clone();
+    let url = serve_http(move |req| match req["method"].as_str().unwrap() {
+        "sendTransaction" => {
+            sends_.fetch_add(1, Ordering::SeqCst);
+            sent.notify_one();
+            rpc_result(&req, signature.to_string().into())
+        }
+        "getBlockHeight" => {
+            let height = block_height.fetch_add(20, Ordering::SeqCst);
+            rpc_result(&req, height.into())
+        }
+        "getSignatureStatuses" => {
+            rpc_result(&req, json!({"context": {"slot": 42}, "value": [null]}))
+        }
+        _ => rpc_error(&req, -32601, "Method not found"),
+    })
+    .await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let config = SendAndConfirmConfig {
+        resend_every: Duration::from_millis(50),
+        last_valid_block_height: Some(130),
+        ..Default::default()
+    };
+    let confirmation = client.send_and_confirm(&http, &tx, config).await.unwrap();
+    assert_eq!(confirmation, Confirmation::Expired);
+    // Sent first, then resent at the heights 100 and 120
+    assert_eq!(sends.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn transient_http_failures() {
+    let payer = Keypair::new();
+    let tx = system_transaction::transfer(&payer, &payer.pubkey(), 1, Hash::new_unique());
+    let signature = tx.signatures[0];
+
+    let (ws_url, _, sent) = serve_ws(None).await;
+    let sends = Arc::new(AtomicU64::new(0));
+    let heights = Arc::new(AtomicU64::new(0));
+    let statuses = Arc::new(AtomicU64::new(0));
+
+    let (sends_, statuses_) = (sends.clone(), statuses.clone());
+    let url = serve_http(move |req| match req["method"].as_str().unwrap() {
+        "sendTransaction" => {
+            sends_.fetch_add(1, Ordering::SeqCst);
+            sent.notify_one();
+            rpc_result(&req, signature.to_string().into())
+        }
+        // Fails once, then 100, 120, 140...
+        "getBlockHeight" => match heights.fetch_add(1, Ordering::SeqCst) {
+            0 => rpc_error(&req, -32005, "Node is behind"),
+            n => rpc_result(&req, (80 + n * 20).into()),
+        },
+        "getSignatureStatuses" => match statuses_.fetch_add(1, Ordering::SeqCst) {
+            0 => rpc_error(&req, -32005, "Node is behind"),
+            _ => rpc_result(&req, json!({"context": {"slot": 42}, "value": [null]})),
+        },
+        _ => rpc_error(&req, -32601, "Method not found"),
+    })
+    .await;
+
+    let mut builder = ClientBuilder::new();
+    builder.url(&url).ws_url(&ws_url);
+    let client = builder.build().await.unwrap();
+    let http = builder.build_http().unwrap();
+
+    let config = SendAndConfirmConfig {
+        resend_every: Duration::from_millis(50),
+        last_valid_block_height: Some(130),
+        ..Default::default()
+    };
+    let confirmation = client.send_and_confirm(&http, &tx, config).await.unwrap();
+    assert_eq!(confirmation, Confirmation::Expired);
+    // Sent first, then resent after the failed check and at the heights 100 and 120
+    assert_eq!(sends.load(Ordering::SeqCst), 4);
+    assert_eq!(statuses.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::{from_str, Value};
+use solana_client_async::prelude::*;
+use std::time::Duration;
+use tungstenite::Message;
+
+/// Wait for the close frame, after the remaining requests.
+async fn recv_close(ws: &mut ServerStream) -> (CloseCode, String) {
+    loop {
+        match ws.next().await.unwrap().unwrap() {
+            Message::Close(Some(frame)) => return (frame.code, frame.reason.into_owned()),
+            Message::Close(None) => panic!("Close frame without a code"),
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn shutdown() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 7.into()).await;
+        let mut requests = vec![];
+
+        // The getSlot request is never answered, and may not even be sent
+        let close = loop {
+            match ws.next().await.unwrap().unwrap() {
+                Message::Text(text) => requests.push(from_str::<Value>(&text).unwrap()),
+                Message::Close(Some(frame)) => break (frame.code, frame.reason.into_owned()),
+                Message::Ping(_) | Message::Pong(_) => continue,
+                msg => panic!("Unexpected message {:?}", msg),
+            }
+        };
+        let unsubscribe = requests.last().unwrap();
+        assert_eq!(unsubscribe["method"], "slotUnsubscribe");
+        assert_eq!(unsubscribe["params"][0], 7);
+        // Flush the close acknowledgement
+        assert!(ws.next().await.is_none());
+        close
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&url)
+        .reconnect_policy(ReconnectPolicy::never())
+        .build()
+        .await
+        .unwrap();
+
+    let mut slots = client.slot_subscribe().await.unwrap();
+    let awaiter = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap();
+
+    client
+        .shutdown(CloseCode::Away, "bye", Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        awaiter.await.unwrap_err(),
+        SolanaClientError::Shutdown
+    ));
+    assert!(slots.next().await.is_none());
+    assert!(matches!(client.closed().await, SolanaClientError::Shutdown));
+    assert!(matches!(
+        client
+            .shutdown(CloseCode::Away, "bye", Duration::from_secs(5))
+            .await,
+        Err(SolanaClientError::BackgroundProcessExited)
+    ));
+
+    let (code, reason) = server.await.unwrap();
+    assert_eq!(code, CloseCode::Away);
+    assert_eq!(reason, "bye");
+}
+
+#[tokio::test]
+async fn close_on_drop() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        recv_close(&mut ws).await
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    drop(client);
+
+    let (code, _) = server.await.unwrap();
+    assert_eq!(code, CloseCode::Normal);
+}
+
+#[tokio::test]
+async fn shutdown_while_reconnecting() {
+    let (listener, url) = listen().await;
+
+    // The server goes away for good, the client keeps reconnecting
+    let server = tokio::spawn(async move {
+        drop(accept(&listener).await);
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    server.await.unwrap();
+
+    let awaiter = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        client.shutdown(CloseCode::Away, "bye", Duration::from_secs(5)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert!(matches!(
+        awaiter.await.unwrap_err(),
+        SolanaClientError::Shutdown
+    ));
+    assert!(matches!(client.closed().await, SolanaClientError::Shutdown));
+}
+
+#[tokio::test]
+async fn drop_while_reconnecting() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        drop(accept(&listener).await);
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    server.await.unwrap();
+
+    let task = client.join_handle().unwrap();
+    assert!(client.clone().join_handle().is_none());
+    // Let the background process notice the disconnect
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .unwrap()
+        .unwrap();
+}
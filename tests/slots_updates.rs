@@ -0,0 +1,65 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::json;
+use solana_client::rpc_response::SlotUpdate;
+use solana_client_async::prelude::*;
+
+#[tokio::test]
+async fn slots_updates() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotsUpdatesSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+
+        let created = json!({"type": "createdBank", "slot": 101, "parent": 100, "timestamp": 1});
+        notify(&mut ws, "slotsUpdatesNotification", 10, created).await;
+        let frozen = json!({
+            "type": "frozen",
+            "slot": 101,
+            "timestamp": 2,
+            "stats": {
+                "numTransactionEntries": 4,
+                "numSuccessfulTransactions": 3,
+                "numFailedTransactions": 1,
+                "maxTransactionsPerEntry": 2,
+            },
+        });
+        notify(&mut ws, "slotsUpdatesNotification", 10, frozen).await;
+
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotsUpdatesUnsubscribe");
+        assert_eq!(req["params"][0], 10);
+        respond(&mut ws, &req["id"], true.into()).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let mut updates = client.slots_updates_subscribe().await.unwrap();
+
+    match updates.next().await.unwrap().unwrap() {
+        SlotUpdate::CreatedBank { slot, parent, .. } => assert_eq!((slot, parent), (101, 100)),
+        update => panic!("Unexpected update {:?}", update),
+    }
+    match updates.next().await.unwrap().unwrap() {
+        SlotUpdate::Frozen { slot, stats, .. } => {
+            assert_eq!(slot, 101);
+            assert_eq!(stats.num_failed_transactions, 1);
+        }
+        update => panic!("Unexpected update {:?}", update),
+    }
+
+    let unsubscribed = client
+        .slots_updates_unsubscribe(updates.id())
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+    assert!(unsubscribed);
+
+    server.await.unwrap();
+}
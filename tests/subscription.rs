@@ -0,0 +1,103 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use solana_client_async::prelude::*;
+
+#[tokio::test]
+async fn subscription_routing() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 7.into()).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "rootSubscribe");
+        respond(&mut ws, &req["id"], 8.into()).await;
+
+        notify(&mut ws, "rootNotification", 8, 42.into()).await;
+        notify(&mut ws, "slotNotification", 7, slot_info(100)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let mut slots = client.slot_subscribe().await.unwrap();
+    let mut roots = client.root_subscribe().await.unwrap();
+    assert_ne!(slots.id(), roots.id());
+
+    assert_eq!(slots.next().await.unwrap().unwrap().slot, 100);
+    assert_eq!(roots.next().await.unwrap().unwrap(), 42);
+
+    let _ws = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn unsubscribe_on_drop() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 7.into()).await;
+
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotUnsubscribe");
+        assert_eq!(req["params"], serde_json::json!([7]));
+        respond(&mut ws, &req["id"], true.into()).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let subscription = client.slot_subscribe().await.unwrap();
+    drop(subscription);
+
+    let _ws = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn notification_before_response() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        notify(&mut ws, "slotNotification", 7, slot_info(100)).await;
+        respond(&mut ws, &req["id"], 7.into()).await;
+        notify(&mut ws, "slotNotification", 7, slot_info(101)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let mut slots = client.slot_subscribe().await.unwrap();
+    assert_eq!(slots.next().await.unwrap().unwrap().slot, 100);
+    assert_eq!(slots.next().await.unwrap().unwrap().slot, 101);
+
+    let _ws = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn unsubscribe_unknown() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        // The unknown id is not forwarded
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "rootSubscribe");
+        respond(&mut ws, &req["id"], 8.into()).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let unsubscribed = client.slot_unsubscribe(42).await.unwrap().await.unwrap();
+    assert!(!unsubscribed);
+
+    client.root_subscribe().await.unwrap();
+    let _ws = server.await.unwrap();
+}
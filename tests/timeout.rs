@@ -0,0 +1,93 @@
+mod common;
+
+use common::*;
+use serde_json::Value;
+use solana_client_async::prelude::*;
+use std::time::Duration;
+
+#[tokio::test]
+async fn request_timeout() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        // Never answered
+        recv_request(&mut ws).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 100.into()).await;
+        recv_request(&mut ws).await;
+        ws
+    });
+
+    let client = ClientBuilder::new()
+        .ws_url(&url)
+        .request_timeout(Duration::from_millis(100))
+        .build()
+        .await
+        .unwrap();
+
+    let awaiter = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap();
+    assert!(matches!(awaiter.await, Err(SolanaClientError::Timeout)));
+
+    let awaiter = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap();
+    assert_eq!(awaiter.await.unwrap(), 100);
+
+    let awaiter = client
+        .request::<_, u64>("getSlot", &Value::Null)
+        .await
+        .unwrap()
+        .timeout(Duration::from_millis(10));
+    assert!(matches!(awaiter.await, Err(SolanaClientError::Timeout)));
+
+    let _ws = server.await.unwrap();
+}
+
+#[tokio::test]
+async fn subscribe_timeout() {
+    let (listener, url) = listen().await;
+    let (answer_tx, answer_rx) = tokio::sync::oneshot::channel::<()>();
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        // Answered once the awaiter timed out
+        answer_rx.await.unwrap();
+        respond(&mut ws, &req["id"], 7.into()).await;
+        notify(&mut ws, "slotNotification", 7, slot_info(100)).await;
+
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotUnsubscribe");
+        assert_eq!(req["params"][0], 7);
+        respond(&mut ws, &req["id"], true.into()).await;
+        ws
+    });
+
+    let mut client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+
+    let awaiter = client
+        .request::<_, u64>("slotSubscribe", &Value::Null)
+        .await
+        .unwrap()
+        .timeout(Duration::from_millis(10));
+    assert!(matches!(awaiter.await, Err(SolanaClientError::Timeout)));
+    answer_tx.send(()).unwrap();
+
+    // Unsubscribed on the server
+    let _ws = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    // The notification of the abandoned subscription is not delivered
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), client.recv::<Value>())
+            .await
+            .is_err()
+    );
+}
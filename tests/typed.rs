@@ -0,0 +1,211 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use serde_json::json;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client_async::errors::Result as MyResult;
+use solana_client_async::prelude::*;
+use solana_sdk::account::Account as RawAccount;
+use solana_sdk::pubkey::Pubkey;
+
+/// An Anchor style account holding a little endian counter.
+#[derive(Debug, PartialEq)]
+struct Counter(u64);
+
+impl AccountDecode for Counter {
+    fn discriminator() -> Option<[u8; 8]> {
+        Some(anchor_discriminator("Counter"))
+    }
+
+    fn decode(data: &[u8]) -> MyResult<Self> {
+        let bytes = data
+            .get(..8)
+            .ok_or_else(|| SolanaClientError::Decode("Too short".into()))?;
+        Ok(Counter(u64::from_le_bytes(bytes.try_into().unwrap())))
+    }
+}
+
+fn counter_data(count: u64) -> Vec<u8> {
+    let mut data = anchor_discriminator("Counter").to_vec();
+    data.extend_from_slice(&count.to_le_bytes());
+    data
+}
+
+fn encode(data: Vec<u8>) -> UiAccount {
+    let account = RawAccount {
+        lamports: 1,
+        data,
+        owner: Pubkey::new_unique(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    UiAccount::encode(
+        &Pubkey::default(),
+        &account,
+        UiAccountEncoding::Base64,
+        None,
+        None,
+    )
+}
+
+#[test]
+fn discriminator() {
+    // sha256("account:Counter")[..8]
+    assert_eq!(
+        anchor_discriminator("Counter"),
+        [255, 176, 4, 245, 188, 253, 124, 25]
+    );
+    assert_eq!(
+        decode_account::<Counter>(&counter_data(3)).unwrap(),
+        Counter(3)
+    );
+
+    let mut data = counter_data(3);
+    data[0] ^= 1;
+    assert!(matches!(
+        decode_account::<Counter>(&data),
+        Err(SolanaClientError::Decode(_))
+    ));
+}
+
+#[tokio::test]
+async fn account_subscribe_typed() {
+    let (listener, url) = listen().await;
+    let pubkey = Pubkey::new_unique();
+
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "accountSubscribe");
+        assert_eq!(req["params"][1]["encoding"], "base64");
+        respond(&mut ws, &req["id"], 10.into()).await;
+
+        let mut wrong = counter_data(2);
+        wrong[..8].copy_from_slice(&anchor_discriminator("Other"));
+        for (slot, data) in [(5, counter_data(1)), (6, wrong), (7, counter_data(3))] {
+            let resp = json!({"context": {"slot": slot}, "value": encode(data)});
+            notify(&mut ws, "accountNotification", 10, resp).await;
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let counters = client
+        .account_subscribe_typed::<Counter>(&pubkey, None)
+        .await
+        .unwrap();
+    futures::pin_mut!(counters);
+
+    assert_eq!(
+        counters.next().await.unwrap().unwrap(),
+        (5, pubkey, Counter(1))
+    );
+    // The failure does not end the stream
+    match counters.next().await.unwrap() {
+        Err(SolanaClientError::Decode(e)) => assert!(e.contains("at slot 6"), "{}", e),
+        item => panic!("Unexpected item {:?}", item),
+    }
+    assert_eq!(
+        counters.next().await.unwrap().unwrap(),
+        (7, pubkey, Counter(3))
+    );
+}
+
+#[tokio::test]
+async fn program_subscribe_typed() {
+    let (listener, url) = listen().await;
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "programSubscribe");
+        assert_eq!(req["params"][1]["encoding"], "base64");
+        respond(&mut ws, &req["id"], 10.into()).await;
+
+        for (slot, pubkey, count) in [(5, a, 1), (6, b, 2)] {
+            let value =
+                json!({"pubkey": pubkey.to_string(), "account": encode(counter_data(count))});
+            let resp = json!({"context": {"slot": slot}, "value": value});
+            notify(&mut ws, "programNotification", 10, resp).await;
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let counters = client
+        .program_subscribe_typed::<Counter>(&Pubkey::new_unique(), None)
+        .await
+        .unwrap();
+    futures::pin_mut!(counters);
+
+    assert_eq!(counters.next().await.unwrap().unwrap(), (5, a, Counter(1)));
+    assert_eq!(counters.next().await.unwrap().unwrap(), (6, b, Counter(2)));
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn borsh() {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct Config {
+        admin: [u8; 32],
+        name: String,
+    }
+
+    impl AccountDecode for Config {
+        fn discriminator() -> Option<[u8; 8]> {
+            Some(anchor_discriminator("Config"))
+        }
+
+        fn decode(data: &[u8]) -> MyResult<Self> {
+            decode_borsh(data)
+        }
+    }
+
+    let config = Config {
+        admin: [1; 32],
+        name: "pool".into(),
+    };
+    let mut data = config.try_to_vec().unwrap();
+    // Trailing space of the account
+    data.extend_from_slice(&[0; 16]);
+    assert_eq!(decode_account::<Borsh<Config>>(&data).unwrap().0, config);
+
+    let mut anchor = anchor_discriminator("Config").to_vec();
+    anchor.extend_from_slice(&data);
+    assert_eq!(decode_account::<Config>(&anchor).unwrap(), config);
+    assert!(decode_account::<Config>(&data).is_err());
+}
+
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+struct Market {
+    base: u64,
+    quote: u64,
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Market {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Market {}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+fn pod() {
+    let market = Market { base: 1, quote: 2 };
+    let mut data = vec![0];
+    data.extend_from_slice(bytemuck::bytes_of(&market));
+
+    // Unaligned
+    assert_eq!(decode_account::<Pod<Market>>(&data[1..]).unwrap().0, market);
+    assert_eq!(decode_pod::<Market>(&data[1..]).unwrap(), market);
+    assert!(matches!(
+        decode_pod::<Market>(&data[1..12]),
+        Err(SolanaClientError::Decode(_))
+    ));
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+use futures::StreamExt;
+use solana_client_async::prelude::*;
+use std::time::Duration;
+
+#[tokio::test]
+async fn resubscribe_on_stall() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 10.into()).await;
+
+        // Silent until the client resubscribes
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotUnsubscribe");
+        assert_eq!(req["params"][0], 10);
+        respond(&mut ws, &req["id"], true.into()).await;
+
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 11.into()).await;
+        notify(&mut ws, "slotNotification", 11, slot_info(100)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let watchdog = Watchdog::new(Duration::from_millis(300), OnStall::Resubscribe);
+    let mut slots = client
+        .with_watchdog(Some(watchdog))
+        .slot_subscribe()
+        .await
+        .unwrap();
+    let subscription_id = slots.id();
+
+    assert!(matches!(
+        slots.next().await.unwrap(),
+        Err(SolanaClientError::Stalled(_))
+    ));
+    let slot = slots.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 100);
+    assert_eq!(slots.id(), subscription_id);
+
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn reconnect_on_stall() {
+    let (listener, url) = listen().await;
+
+    let server = tokio::spawn(async move {
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        respond(&mut ws, &req["id"], 10.into()).await;
+
+        // Silent until the client reconnects
+        let mut ws = accept(&listener).await;
+        let req = recv_request(&mut ws).await;
+        assert_eq!(req["method"], "slotSubscribe");
+        respond(&mut ws, &req["id"], 20.into()).await;
+        notify(&mut ws, "slotNotification", 20, slot_info(100)).await;
+        ws
+    });
+
+    let client = ClientBuilder::new().ws_url(&url).build().await.unwrap();
+    let watchdog = Watchdog::new(Duration::from_millis(300), OnStall::Reconnect);
+    let mut slots = client
+        .with_watchdog(Some(watchdog))
+        .slot_subscribe()
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        slots.next().await.unwrap(),
+        Err(SolanaClientError::Stalled(_))
+    ));
+    let slot = slots.next().await.unwrap().unwrap();
+    assert_eq!(slot.slot, 100);
+
+    server.await.unwrap();
+}